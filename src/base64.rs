@@ -0,0 +1,73 @@
+/// Standard (RFC 4648) base64 alphabet
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode standard base64 with optional padding; None for characters outside the
+/// alphabet or a length no encoder produces
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let trimmed = encoded.trim_end_matches('=').as_bytes();
+    if trimmed.len() % 4 == 1 || encoded.len() - trimmed.len() > 2 {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(trimmed.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for &c in trimmed {
+        let sextet = ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 6) | sextet;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_padding_length() {
+        for (decoded, encoded) in [
+            (&b""[..], ""),
+            (b"a", "YQ=="),
+            (b"ab", "YWI="),
+            (b"abc", "YWJj"),
+            (b"Aladdin:open sesame", "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+            (&[0xfb, 0xff, 0xfe], "+//+"),
+        ] {
+            assert_eq!(encode(decoded), encoded);
+            assert_eq!(decode(encoded).as_deref(), Some(decoded));
+        }
+        assert_eq!(decode("YWI").as_deref(), Some(&b"ab"[..]));
+    }
+
+    #[test]
+    fn rejects_invalid_base64() {
+        assert_eq!(decode("YW-="), None);
+        assert_eq!(decode("Y"), None);
+        assert_eq!(decode("YQ==="), None);
+    }
+}
@@ -1,77 +1,337 @@
 use std::collections::HashMap;
-use std::fs;
-use std::io::Cursor;
-use zed_extension_api::{self as zed, http_client, Result};
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::checksum::ExpectedDigest;
+use crate::dotnet::DotnetRuntimes;
+use crate::error::InstallError;
+use crate::file_system::{FileSystem, WorkDir};
+use crate::http::{self, HttpClient, ZedHttpClient};
+use crate::install_lock::InstallLock;
+use crate::install_manifest::InstallManifest;
 use crate::logger;
-use crate::path_utils;
-use crate::version_config::VersionDirConfig;
+use crate::maintenance::{self, GcReport, InstallUsage, RetentionPolicy, WorkDirEntry};
+use crate::progress::{InstallProgress, InstallStatus};
+use crate::release_source::{
+    self, AssetLocation, AssetRequest, ReleaseLookup, ReleaseSource, SourceHost, Upstream,
+    VersionQuery,
+};
+use crate::tool_manifest::ArchiveKind;
+use crate::update_state::UpdateState;
+use crate::validation::{self, ExpectedFile, ExtractedFile, FileFormat};
+use crate::version::{Version, VersionReq};
+use crate::version_config::{auth_headers, HttpAuth, VersionDirConfig};
+
+/// Attempts at downloading an asset before giving up
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+
+/// Archive formats that release assets are published in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Recognize an archive by its first bytes
+    fn from_magic(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"PK\x03\x04") {
+            Some(Self::Zip)
+        } else if data.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+
+    /// Detect the archive format from the asset name, falling back to the format the
+    /// tool's manifest declares and then the magic bytes, for URLs that don't carry a
+    /// file extension (e.g. NuGet package downloads)
+    fn detect(
+        asset_name: &str,
+        declared: Option<ArchiveKind>,
+        data: &[u8],
+    ) -> Result<Self, InstallError> {
+        let name = asset_name
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Ok(Self::TarGz);
+        }
+        if name.ends_with(".zip") || name.ends_with(".nupkg") {
+            return Ok(Self::Zip);
+        }
+        match declared {
+            Some(ArchiveKind::Zip) => return Ok(Self::Zip),
+            Some(ArchiveKind::TarGz) => return Ok(Self::TarGz),
+            None => {}
+        }
+
+        if let Some(format) = Self::from_magic(data) {
+            Ok(format)
+        } else {
+            Err(InstallError::UnexpectedContent {
+                url: asset_name.to_string(),
+                message: format!(
+                    "not a zip or tar.gz archive (header: {:02x?})",
+                    &data[..data.len().min(4)]
+                ),
+            })
+        }
+    }
+}
+
+/// Per-call options for [`BinaryManager::get_version_dir`]
+#[derive(Default)]
+pub struct InstallOptions {
+    /// Version requirement from settings. When set, only matching versions are used,
+    /// and a matching local install is kept rather than upgraded to a newer release.
+    pub version: Option<VersionReq>,
+    /// Installed .NET runtimes, used to prefer package versions that can run here.
+    /// Empty when unknown or irrelevant (native tools).
+    pub runtimes: DotnetRuntimes,
+    /// Never touch the network; only use what is already installed (or `local_archive`)
+    pub offline: bool,
+    /// Install from this archive on disk instead of downloading
+    pub local_archive: Option<String>,
+    /// Reuse a previous update check younger than this instead of asking the source again;
+    /// zero always checks
+    pub update_check_interval: Duration,
+    /// Previous versions to keep installed after an update
+    pub retain_versions: usize,
+    /// Version that last started successfully; never cleaned up
+    pub known_good: Option<String>,
+}
+
+/// A backend to install from, in the order the configured sources are tried
+struct InstallSource {
+    backend: Box<dyn ReleaseSource>,
+    /// Refuse assets without a published digest. Only the manifest's own source is held
+    /// to its `require_digest`; mirrors and private feeds often publish none.
+    require_digest: bool,
+}
+
+/// An archive extracted into a staging directory, and where it came from
+struct StagedInstall {
+    /// Download URL or side-loaded archive path
+    source: String,
+    digest: Option<ExpectedDigest>,
+    files: Vec<ExtractedFile>,
+}
 
 pub struct BinaryManager {
     version_dir_cache: HashMap<String, String>,
+    fs: Arc<dyn FileSystem>,
+    http: Box<dyn HttpClient>,
+    releases: Box<dyn ReleaseLookup>,
 }
 
 impl BinaryManager {
     pub fn new() -> Self {
+        Self::with_host(WorkDir, ZedHttpClient, Upstream)
+    }
+
+    /// A manager that reaches the work directory, the network and release sources
+    /// through the given implementations
+    pub fn with_host(
+        fs: impl FileSystem + 'static,
+        http: impl HttpClient + 'static,
+        releases: impl ReleaseLookup + 'static,
+    ) -> Self {
         Self {
             version_dir_cache: HashMap::new(),
+            fs: Arc::new(fs),
+            http: Box::new(http),
+            releases: Box::new(releases),
         }
     }
 
-    /// Download file from URL using HTTP
-    fn download_file_http(url: &str) -> Result<Vec<u8>> {
-        logger::Logger::debug(&format!("download_file_http: downloading from {}", url));
+    /// The work directory installs live in
+    pub fn fs(&self) -> &dyn FileSystem {
+        self.fs.as_ref()
+    }
 
-        let request = http_client::HttpRequest {
-            method: http_client::HttpMethod::Get,
-            url: url.to_string(),
-            headers: Default::default(),
-            body: None,
-            redirect_policy: http_client::RedirectPolicy::FollowAll,
+    /// What release sources look up versions and assets through
+    fn source_host(&self) -> SourceHost<'_> {
+        SourceHost {
+            fs: self.fs.as_ref(),
+            http: self.http.as_ref(),
+        }
+    }
+
+    /// Stream `url` into `partial_path`, sending `auth` as a request header. Bytes already
+    /// in `partial_path` from an interrupted download are kept when the server can resume
+    /// after them (see `resume_offset`); a file already as large as `expected_size` isn't
+    /// fetched again.
+    fn download_to_file(
+        &self,
+        url: &str,
+        auth: Option<&HttpAuth>,
+        partial_path: &str,
+        expected_size: Option<u64>,
+        progress: &dyn InstallProgress,
+    ) -> Result<(), InstallError> {
+        let partial_len = self
+            .fs
+            .metadata(partial_path)
+            .map(|stat| stat.len)
+            .unwrap_or(0);
+        if partial_len > 0 && expected_size.is_some_and(|size| partial_len >= size) {
+            logger::Logger::debug(&format!(
+                "download_to_file: {} already holds all {} bytes",
+                partial_path, partial_len
+            ));
+            return Ok(());
+        }
+
+        let resume_from = if partial_len > 0 {
+            match self.resume_offset(url, auth, partial_path, partial_len)? {
+                Some(offset) => offset,
+                None => return Ok(()),
+            }
+        } else {
+            0
         };
 
-        let response =
-            http_client::fetch(&request).map_err(|e| format!("HTTP fetch failed: {}", e))?;
+        let mut headers = auth_headers(auth);
+        if resume_from > 0 {
+            logger::Logger::debug(&format!(
+                "download_to_file: resuming {} at byte {}",
+                url, resume_from
+            ));
+            headers.push(("Range".to_string(), format!("bytes={}-", resume_from)));
+        } else {
+            logger::Logger::debug(&format!("download_to_file: downloading from {}", url));
+        }
 
-        logger::Logger::debug(&format!(
-            "download_file_http: received {} bytes",
-            response.body.len()
-        ));
+        let mut stream = self
+            .http
+            .fetch_stream(&http::get_request(url, headers))
+            .map_err(|e| InstallError::from_fetch(url, &e))?;
+
+        let mut file = if resume_from > 0 {
+            self.fs.append(partial_path)
+        } else {
+            self.fs.create(partial_path)
+        }
+        .map_err(|e| InstallError::io(partial_path, &e))?;
+        let mut received = 0u64;
+        while let Some(chunk) = stream
+            .next_chunk()
+            .map_err(|e| InstallError::from_fetch(url, &e))?
+        {
+            file.write_all(&chunk)
+                .map_err(|e| InstallError::io(partial_path, &e))?;
+            received += chunk.len() as u64;
+            progress.advance();
+        }
+
+        logger::Logger::debug(&format!("download_to_file: received {} bytes", received));
 
         // Check if we actually got data
-        if response.body.is_empty() {
-            return Err("downloaded file is empty".to_string());
+        if received == 0 && resume_from == 0 {
+            return Err(InstallError::Network {
+                url: url.to_string(),
+                message: "downloaded file is empty".to_string(),
+            });
         }
 
-        Ok(response.body)
+        Ok(())
     }
 
-    /// Extract ZIP file using the zip crate (pure Rust, no C dependencies)
-    fn extract_zip(zip_data: &[u8], destination: &str) -> Result<()> {
-        logger::Logger::debug(&format!(
-            "extract_zip: extracting {} bytes to {}",
-            zip_data.len(),
-            destination
+    /// Where to continue an interrupted download of `url` holding `partial_len` bytes, or
+    /// None when `partial_path` is complete. The last byte already received is requested
+    /// again: a partial response's `Content-Range` tells whether the file is complete, can
+    /// be resumed, or changed and has to start over from 0. A server that ignores ranges
+    /// answers with the whole file, which replaces the partial download.
+    fn resume_offset(
+        &self,
+        url: &str,
+        auth: Option<&HttpAuth>,
+        partial_path: &str,
+        partial_len: u64,
+    ) -> Result<Option<u64>, InstallError> {
+        let mut headers = auth_headers(auth);
+        headers.push((
+            "Range".to_string(),
+            format!("bytes={}-{}", partial_len - 1, partial_len - 1),
         ));
+        let response = match self.http.fetch(&http::get_request(url, headers)) {
+            Ok(response) => response,
+            Err(e) => {
+                return match InstallError::from_fetch(url, &e) {
+                    // Even the last byte is out of range: the file got shorter
+                    InstallError::HttpStatus { status: 416, .. } => {
+                        logger::Logger::debug(&format!(
+                            "resume_offset: {} changed since the partial download, starting over",
+                            url
+                        ));
+                        Ok(Some(0))
+                    }
+                    e => Err(e),
+                };
+            }
+        };
+
+        let Some((start, total)) = http::content_range(&response.headers) else {
+            logger::Logger::debug(&format!(
+                "resume_offset: {} doesn't support resuming, received the whole file",
+                url
+            ));
+            self.fs
+                .write(partial_path, &response.body)
+                .map_err(|e| InstallError::io(partial_path, &e))?;
+            return Ok(None);
+        };
+        match total {
+            _ if start != partial_len - 1 => Ok(Some(0)),
+            Some(total) if total == partial_len => {
+                logger::Logger::debug(&format!(
+                    "resume_offset: {} already holds all {} bytes",
+                    partial_path, partial_len
+                ));
+                Ok(None)
+            }
+            Some(total) if total < partial_len => Ok(Some(0)),
+            _ => Ok(Some(partial_len)),
+        }
+    }
+
+    /// Extract ZIP file using the zip crate (pure Rust, no C dependencies)
+    fn extract_zip(
+        &self,
+        zip_data: impl Read + Seek,
+        destination: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<Vec<ExtractedFile>, InstallError> {
+        logger::Logger::debug(&format!("extract_zip: extracting to {}", destination));
 
         // Ensure destination directory exists
-        fs::create_dir_all(destination)
-            .map_err(|e| format!("failed to create destination directory: {}", e))?;
+        self.fs
+            .create_dir_all(destination)
+            .map_err(|e| InstallError::io(destination, &e))?;
 
-        // Extract ZIP from memory
-        let cursor = Cursor::new(zip_data);
-        let mut archive = zip::ZipArchive::new(cursor)
-            .map_err(|e| format!("failed to open zip archive: {}", e))?;
+        let mut archive = zip::ZipArchive::new(zip_data).map_err(|e| {
+            InstallError::ArchiveCorrupt(format!("failed to open zip archive: {}", e))
+        })?;
 
         logger::Logger::debug(&format!(
             "extract_zip: archive has {} entries",
             archive.len()
         ));
 
+        let mut extracted = Vec::new();
+
         for i in 0..archive.len() {
-            let mut file = archive
-                .by_index(i)
-                .map_err(|e| format!("failed to read zip entry {}: {}", i, e))?;
+            progress.advance();
+            let mut file = archive.by_index(i).map_err(|e| {
+                InstallError::ArchiveCorrupt(format!("failed to read zip entry {}: {}", i, e))
+            })?;
 
             // Get the file path, handling various path formats
             let file_path_str = if let Some(enclosed_name) = file.enclosed_name() {
@@ -88,7 +348,7 @@ impl BinaryManager {
                 continue;
             }
 
-            let outpath = std::path::PathBuf::from(destination).join(&file_path_str);
+            let outpath = format!("{}/{}", destination, file_path_str);
 
             logger::Logger::debug(&format!(
                 "extract_zip: processing entry: {} (size: {} bytes, is_dir: {})",
@@ -99,24 +359,31 @@ impl BinaryManager {
 
             if file.is_dir() {
                 // Directory entry
-                fs::create_dir_all(&outpath)
-                    .map_err(|e| format!("failed to create directory {}: {}", file_path_str, e))?;
+                self.fs
+                    .create_dir_all(&outpath)
+                    .map_err(|e| InstallError::io(&file_path_str, &e))?;
             } else {
                 // File entry
-                if let Some(parent) = outpath.parent() {
-                    fs::create_dir_all(parent).map_err(|e| {
-                        format!(
-                            "failed to create parent directory for {}: {}",
-                            file_path_str, e
-                        )
-                    })?;
+                if let Some((parent, _)) = outpath.rsplit_once('/') {
+                    self.fs
+                        .create_dir_all(parent)
+                        .map_err(|e| InstallError::io(&file_path_str, &e))?;
                 }
 
-                let mut outfile = fs::File::create(&outpath)
-                    .map_err(|e| format!("failed to create file {}: {}", file_path_str, e))?;
+                let mut outfile = self
+                    .fs
+                    .create(&outpath)
+                    .map_err(|e| InstallError::io(&file_path_str, &e))?;
 
                 std::io::copy(&mut file, &mut outfile)
-                    .map_err(|e| format!("failed to copy file {}: {}", file_path_str, e))?;
+                    .map_err(|e| InstallError::extract(&file_path_str, &e))?;
+                let executable = file.unix_mode().is_some_and(|mode| mode & 0o111 != 0);
+                Self::record_extracted(
+                    &mut extracted,
+                    file_path_str.clone(),
+                    file.size(),
+                    executable,
+                );
 
                 logger::Logger::debug(&format!(
                     "extract_zip: successfully extracted {} ({} bytes)",
@@ -127,324 +394,1905 @@ impl BinaryManager {
         }
 
         logger::Logger::debug("extract_zip: extraction completed successfully");
-        Ok(())
+        Ok(extracted)
     }
 
-    fn download_with_retry(url: &str, destination: &str, max_retries: usize) -> Result<()> {
+    /// Resolve a tar entry path inside the destination, mirroring the rules of
+    /// `ZipFile::enclosed_name`: absolute paths and paths escaping through `..` are rejected
+    fn enclosed_path(path: &Path) -> Option<PathBuf> {
+        if path.as_os_str().to_string_lossy().contains('\0') {
+            return None;
+        }
+
+        let mut enclosed = PathBuf::new();
+        let mut depth = 0usize;
+        for component in path.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir => return None,
+                Component::ParentDir => {
+                    depth = depth.checked_sub(1)?;
+                    enclosed.pop();
+                }
+                Component::Normal(part) => {
+                    depth += 1;
+                    enclosed.push(part);
+                }
+                Component::CurDir => {}
+            }
+        }
+        Some(enclosed)
+    }
+
+    /// Extract gzip-compressed tarball using the tar and flate2 crates (pure Rust, no C dependencies)
+    fn extract_tar_gz(
+        &self,
+        tar_gz_data: impl Read,
+        destination: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<Vec<ExtractedFile>, InstallError> {
+        logger::Logger::debug(&format!("extract_tar_gz: extracting to {}", destination));
+
+        // Ensure destination directory exists
+        self.fs
+            .create_dir_all(destination)
+            .map_err(|e| InstallError::io(destination, &e))?;
+
+        let decoder = flate2::read::GzDecoder::new(tar_gz_data);
+        let mut archive = tar::Archive::new(decoder);
+        let entries = archive
+            .entries()
+            .map_err(|e| InstallError::extract("tar archive", &e))?;
+
+        let mut extracted = Vec::new();
+
+        for (i, entry) in entries.enumerate() {
+            progress.advance();
+            let mut entry =
+                entry.map_err(|e| InstallError::extract(&format!("tar entry {}", i), &e))?;
+
+            let entry_path = entry
+                .path()
+                .map_err(|e| InstallError::extract(&format!("path of tar entry {}", i), &e))?
+                .into_owned();
+
+            // Get the file path, rejecting anything that would land outside the destination
+            let file_path_str = if let Some(enclosed_name) = Self::enclosed_path(&entry_path) {
+                enclosed_name.to_string_lossy().to_string()
+            } else {
+                logger::Logger::warn(&format!(
+                    "extract_tar_gz: skipping entry {} with invalid path",
+                    i
+                ));
+                continue;
+            };
+
+            if file_path_str.is_empty() {
+                continue;
+            }
+
+            let outpath = format!("{}/{}", destination, file_path_str);
+            let entry_type = entry.header().entry_type();
+
+            logger::Logger::debug(&format!(
+                "extract_tar_gz: processing entry: {} (size: {} bytes, type: {:?})",
+                file_path_str,
+                entry.size(),
+                entry_type
+            ));
+
+            if entry_type.is_dir() {
+                // Directory entry
+                self.fs
+                    .create_dir_all(&outpath)
+                    .map_err(|e| InstallError::io(&file_path_str, &e))?;
+            } else if entry_type.is_file() {
+                // File entry
+                if let Some((parent, _)) = outpath.rsplit_once('/') {
+                    self.fs
+                        .create_dir_all(parent)
+                        .map_err(|e| InstallError::io(&file_path_str, &e))?;
+                }
+
+                let mut outfile = self
+                    .fs
+                    .create(&outpath)
+                    .map_err(|e| InstallError::io(&file_path_str, &e))?;
+
+                std::io::copy(&mut entry, &mut outfile)
+                    .map_err(|e| InstallError::extract(&file_path_str, &e))?;
+                let executable = entry.header().mode().is_ok_and(|mode| mode & 0o111 != 0);
+                Self::record_extracted(
+                    &mut extracted,
+                    file_path_str.clone(),
+                    entry.size(),
+                    executable,
+                );
+
+                logger::Logger::debug(&format!(
+                    "extract_tar_gz: successfully extracted {} ({} bytes)",
+                    file_path_str,
+                    entry.size()
+                ));
+            } else {
+                // Links could point outside the destination, and the remaining types
+                // (devices, fifos, pax headers) have no meaning for a tool install
+                logger::Logger::warn(&format!(
+                    "extract_tar_gz: skipping unsupported entry {} ({:?})",
+                    file_path_str, entry_type
+                ));
+            }
+        }
+
+        logger::Logger::debug("extract_tar_gz: extraction completed successfully");
+        Ok(extracted)
+    }
+
+    /// Note a file written during extraction. An archive may list the same path twice,
+    /// in which case the later entry is the one left on disk.
+    fn record_extracted(
+        extracted: &mut Vec<ExtractedFile>,
+        path: String,
+        size: u64,
+        executable: bool,
+    ) {
+        extracted.retain(|file| file.path != path);
+        extracted.push(ExtractedFile {
+            path,
+            size,
+            executable,
+        });
+    }
+
+    /// Set the executable bit on `path`; Windows has no such bit
+    pub fn make_executable(&self, path: &str) -> Result<(), InstallError> {
+        self.fs
+            .make_executable(path)
+            .map_err(|message| InstallError::Permission {
+                path: path.to_string(),
+                message,
+            })
+    }
+
+    /// Mark the files the archive flagged as executable, and the native binaries the
+    /// config requires, executable in `dir`
+    fn mark_executables(
+        &self,
+        dir: &str,
+        expected: &[ExpectedFile],
+        extracted: &[ExtractedFile],
+    ) -> Result<(), InstallError> {
+        let flagged = extracted
+            .iter()
+            .filter(|file| file.executable)
+            .map(|file| file.path.as_str());
+        let native = expected
+            .iter()
+            .filter(|file| file.format == FileFormat::NativeExecutable)
+            .map(|file| file.path.as_str());
+        let mut paths: Vec<&str> = flagged.chain(native).collect();
+        paths.sort_unstable();
+        paths.dedup();
+
+        paths
+            .into_iter()
+            .try_for_each(|path| self.make_executable(&format!("{}/{}", dir, path)))
+    }
+
+    /// Extract an archive file, picking the extractor from the asset name or contents
+    fn extract_archive(
+        &self,
+        asset_name: &str,
+        declared: Option<ArchiveKind>,
+        archive_path: &str,
+        destination: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<Vec<ExtractedFile>, InstallError> {
+        let mut file = self
+            .fs
+            .open(archive_path)
+            .map_err(|e| InstallError::io(archive_path, &e))?;
+        let mut magic = Vec::with_capacity(4);
+        (&mut file)
+            .take(4)
+            .read_to_end(&mut magic)
+            .map_err(|e| InstallError::io(archive_path, &e))?;
+        file.rewind()
+            .map_err(|e| InstallError::io(archive_path, &e))?;
+
+        let format = ArchiveFormat::detect(asset_name, declared, &magic)?;
+        logger::Logger::debug(&format!(
+            "extract_archive: detected {:?} archive for {}",
+            format, asset_name
+        ));
+
+        let reader = BufReader::new(file);
+        match format {
+            ArchiveFormat::Zip => self.extract_zip(reader, destination, progress),
+            ArchiveFormat::TarGz => self.extract_tar_gz(reader, destination, progress),
+        }
+    }
+
+    /// Verify and extract an archive file into `destination`, returning the files written.
+    /// Verification comes first so nothing is written under the version directory
+    /// for an archive that doesn't match its published size and digest.
+    fn install_archive(
+        &self,
+        asset_name: &str,
+        declared: Option<ArchiveKind>,
+        archive_path: &str,
+        destination: &str,
+        expected_digest: Option<&ExpectedDigest>,
+        progress: &dyn InstallProgress,
+    ) -> Result<Vec<ExtractedFile>, InstallError> {
+        if let Some(digest) = expected_digest {
+            digest
+                .verify_file(self.fs.as_ref(), archive_path)
+                .map_err(InstallError::ChecksumMismatch)?;
+            logger::Logger::debug(&format!(
+                "install_archive: verified {:?} digest from {}",
+                digest.algorithm, digest.source
+            ));
+        }
+
+        self.extract_archive(asset_name, declared, archive_path, destination, progress)
+    }
+
+    /// Download and install an archive, retrying failures that another attempt can fix
+    /// with backoff; permanent failures (missing files, bad platforms) fail immediately.
+    /// The download goes to `<destination>.partial` first, so a dropped connection resumes
+    /// where it left off, in a later attempt or a later session.
+    fn download_with_retry(
+        &self,
+        url: &str,
+        declared: Option<ArchiveKind>,
+        auth: Option<&HttpAuth>,
+        destination: &str,
+        expected_digest: Option<&ExpectedDigest>,
+        progress: &dyn InstallProgress,
+    ) -> Result<Vec<ExtractedFile>, InstallError> {
+        let partial_path = format!("{}.partial", destination);
+        let expected_size = expected_digest.and_then(|digest| digest.size);
         let mut attempt = 0;
 
-        while attempt < max_retries {
+        loop {
             attempt += 1;
             logger::Logger::debug(&format!(
                 "download_with_retry: attempting download (attempt {}/{})",
-                attempt, max_retries
+                attempt, DOWNLOAD_ATTEMPTS
             ));
 
-            // use custom HTTP download and ZIP extraction
-            let result = {
-                let zip_data = match Self::download_file_http(url) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        logger::Logger::warn(&format!(
-                            "download_with_retry: attempt {} download failed: {}",
-                            attempt, e
-                        ));
-
-                        if attempt < max_retries {
-                            continue;
-                        } else {
-                            return Err(e);
-                        }
-                    }
-                };
+            progress.report(&InstallStatus::Downloading);
+            let result = self
+                .download_to_file(url, auth, &partial_path, expected_size, progress)
+                .and_then(|()| {
+                    progress.report(&InstallStatus::Extracting);
+                    self.install_archive(
+                        url,
+                        declared,
+                        &partial_path,
+                        destination,
+                        expected_digest,
+                        progress,
+                    )
+                });
 
-                Self::extract_zip(&zip_data, destination)
-            };
-
-            match result {
-                Ok(()) => {
+            let e = match result {
+                Ok(extracted) => {
                     logger::Logger::debug("download_with_retry: download/extraction succeeded");
-                    return Ok(());
+                    self.fs.remove_file(&partial_path).ok();
+                    return Ok(extracted);
                 }
-                Err(e) => {
-                    let error_str = e.to_string();
-                    logger::Logger::warn(&format!(
-                        "download_with_retry: attempt {} failed: {}",
-                        attempt, error_str
-                    ));
-
-                    // Check if this is a retryable error
-                    let is_retryable = error_str.contains("unexpected end of file")
-                        || error_str.contains("extraction")
-                        || error_str.contains("download")
-                        || error_str.contains("incomplete write")
-                        || error_str.contains("failed");
+                Err(e) => e,
+            };
 
-                    if is_retryable && attempt < max_retries {
-                        // Clean up the corrupted directory before retrying
-                        logger::Logger::debug(&format!(
-                            "download_with_retry: cleaning up corrupted directory before retry"
-                        ));
-                        fs::remove_dir_all(destination).ok();
-                        fs::create_dir_all(destination).map_err(|e| {
-                            format!("failed to create directory {}: {}", destination, e)
-                        })?;
+            // Bytes received before a network error are good to resume from; a download
+            // that completed but doesn't verify or extract, or that the server refuses to
+            // resume (416), has to start over
+            let resumable = match &e {
+                InstallError::Network { .. } => true,
+                InstallError::HttpStatus { status, .. } => *status != 416,
+                _ => false,
+            };
+            if !resumable {
+                self.fs.remove_file(&partial_path).ok();
+            }
+            // Clean up the corrupted directory before retrying
+            self.fs.remove_dir_all(destination).ok();
 
-                        logger::Logger::debug(&format!(
-                            "download_with_retry: retrying download (attempt {} of {})",
-                            attempt + 1,
-                            max_retries
-                        ));
-                    } else {
-                        // Either not retryable or max retries exhausted
-                        return Err(error_str);
-                    }
-                }
+            if !e.is_retryable() || attempt >= DOWNLOAD_ATTEMPTS {
+                logger::Logger::warn(&format!(
+                    "download_with_retry: attempt {} failed, giving up: {}",
+                    attempt, e
+                ));
+                return Err(e);
             }
-        }
 
-        // Should not reach here, but return error if we do
-        Err("download retries exhausted".to_string())
+            let backoff = e.backoff(attempt);
+            logger::Logger::warn(&format!(
+                "download_with_retry: attempt {} failed, retrying in {:?}: {}",
+                attempt, backoff, e
+            ));
+            self.http.wait(backoff);
+        }
     }
 
     /// Get the version directory, downloading if necessary
     pub fn get_version_dir(
         &mut self,
         config: &VersionDirConfig,
-        language_server_id: Option<&zed::LanguageServerId>,
-    ) -> Result<String> {
+        options: &InstallOptions,
+        progress: &dyn InstallProgress,
+    ) -> Result<String, InstallError> {
         let fn_name = format!("get_version_dir[{}]", config.prefix);
         logger::Logger::debug(&format!("{}: starting version check", fn_name));
 
-        // Check cache first
-        if let Some(cached_path) = self.version_dir_cache.get(&config.prefix) {
-            logger::Logger::debug(&format!(
-                "{}: found cached version dir: {}",
-                fn_name, cached_path
-            ));
-            return Ok(cached_path.clone());
+        // Pinned, unpinned and side-loaded lookups resolve to different directories
+        let mut cache_key = match &options.version {
+            Some(requirement) => format!("{}@{}", config.prefix, requirement),
+            None => config.prefix.clone(),
+        };
+        if let Some(archive_path) = &options.local_archive {
+            cache_key = format!("{}#{}", cache_key, archive_path);
+        }
+
+        // Check cache first, as long as the install still matches its manifest
+        if let Some(cached_path) = self.version_dir_cache.get(&cache_key).cloned() {
+            if self.is_intact(config, &cached_path, false, &fn_name) {
+                logger::Logger::debug(&format!(
+                    "{}: found cached version dir: {}",
+                    fn_name, cached_path
+                ));
+                return Ok(cached_path);
+            }
+            self.version_dir_cache.remove(&cache_key);
         }
 
         // Try to find the latest local version first
-        let entries =
-            fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
-        let mut latest_local_version = None;
-
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
-            if let Some(name) = entry.file_name().to_str() {
-                if name.starts_with(&config.prefix)
-                    && fs::metadata(&name).map_or(false, |stat| stat.is_dir())
+        let entries = self
+            .fs
+            .read_dir(".")
+            .map_err(|e| InstallError::io(&self.work_dir(), &e))?;
+        // Keep the raw directory suffix alongside the parsed version, so the
+        // directory name is reproduced exactly rather than re-rendered
+        let mut latest_local_version: Option<(Version, String)> = None;
+
+        for name in &entries {
+            if let Some(raw_version) = name
+                .strip_prefix(&format!("{}-", config.prefix))
+                .filter(|_| self.fs.is_dir(name))
+            {
+                let Ok(version) = Version::parse(raw_version) else {
+                    logger::Logger::debug(&format!(
+                        "{}: ignoring directory without a valid version: {}",
+                        fn_name, name
+                    ));
+                    continue;
+                };
+                if !options
+                    .version
+                    .as_ref()
+                    .is_none_or(|requirement| requirement.matches(&version))
+                {
+                    continue;
+                }
+                if latest_local_version
+                    .as_ref()
+                    .is_none_or(|(latest, _)| version > *latest)
                 {
-                    let version = name.trim_start_matches(&format!("{}-", config.prefix));
-                    if latest_local_version
-                        .as_ref()
-                        .map_or(true, |latest: &String| version > latest)
-                    {
-                        latest_local_version = Some(version.to_string());
+                    latest_local_version = Some((version, raw_version.to_string()));
+                }
+            }
+        }
+
+        let pinned = options.version.as_ref().and_then(VersionReq::exact);
+        // Installed version to use instead when the newer remote version can't be installed
+        let mut local_fallback = None;
+        let version = match (&options.version, pinned, latest_local_version) {
+            // A side-loaded archive decides the version itself
+            _ if options.local_archive.is_some() => Self::local_archive_version(config, options)?,
+            // An exact pin needs no release lookup: the version is known
+            (_, Some(pinned), local) => {
+                let version = local
+                    .map(|(_, local_raw)| local_raw)
+                    .unwrap_or_else(|| pinned.to_string());
+                logger::Logger::debug(&format!("{}: using pinned version {}", fn_name, version));
+                version
+            }
+            // A matching local install satisfies a range; never upgrade it silently
+            (Some(requirement), None, Some((_, local_raw))) => {
+                logger::Logger::debug(&format!(
+                    "{}: keeping local version {} matching {}",
+                    fn_name, local_raw, requirement
+                ));
+                local_raw
+            }
+            (Some(requirement), None, None) if options.offline => {
+                return Err(InstallError::Offline(self.offline_error(
+                    config,
+                    &format!("no installed version matches '{}'", requirement),
+                    "<version>",
+                )));
+            }
+            (Some(requirement), None, None) => {
+                let (_, remote_raw) =
+                    self.latest_remote_version(config, options, &fn_name, progress)
+                        .ok_or_else(|| {
+                            InstallError::VersionNotFound(format!(
+                                "No {} version matching '{}' found locally or in {}; set an exact version to install an older release",
+                                config.prefix, requirement, config.version_source
+                            ))
+                        })?;
+                remote_raw
+            }
+            (None, _, latest_local_version) => {
+                let remote_version =
+                    self.throttled_remote_version(config, options, &fn_name, progress);
+
+                // Use the remote version if it's newer than local, otherwise use local
+                if let Some((remote_ver, remote_raw)) = remote_version {
+                    match latest_local_version {
+                        Some((local, local_raw)) if local >= remote_ver => local_raw,
+                        local => {
+                            local_fallback = local.map(|(_, local_raw)| local_raw);
+                            remote_raw
+                        }
                     }
+                } else if options.offline {
+                    latest_local_version
+                        .map(|(_, local_raw)| local_raw)
+                        .ok_or_else(|| {
+                            InstallError::Offline(self.offline_error(
+                                config,
+                                "nothing is installed",
+                                "<version>",
+                            ))
+                        })?
+                } else {
+                    // No network access, fall back to local version
+                    latest_local_version
+                        .map(|(_, local_raw)| local_raw)
+                        .ok_or_else(|| {
+                            // The lookup failure itself was logged; the source is unreachable
+                            InstallError::Network {
+                                url: config.version_source.to_string(),
+                                message: format!(
+                                    "no {} version installed and no version could be looked up",
+                                    config.prefix
+                                ),
+                            }
+                        })?
                 }
             }
+        };
+
+        let result =
+            self.install_version(config, options, &version, &cache_key, &fn_name, progress);
+        match (result, local_fallback) {
+            // A newer version that fails to download or validate mustn't take away the
+            // installed one that works
+            (Err(e), Some(local_raw)) => {
+                logger::Logger::warn(&format!(
+                    "{}: failed to install {} {}, using installed {}: {}",
+                    fn_name, config.prefix, version, local_raw, e
+                ));
+                self.install_version(config, options, &local_raw, &cache_key, &fn_name, progress)
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Use `version` if it is installed, otherwise install it
+    fn install_version(
+        &mut self,
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+        version: &str,
+        cache_key: &str,
+        fn_name: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<String, InstallError> {
+        let version_dir = format!("{}-{}", config.prefix, version);
+
+        // If we already have this version locally, use it. Installs are renamed into place
+        // only once complete, so an existing directory that matches its manifest is sound.
+        if self.is_installed(config, options, &version_dir, fn_name) {
+            logger::Logger::debug(&format!(
+                "{}: validated existing directory: {}",
+                fn_name, version_dir
+            ));
+            return Ok(self.use_version_dir(cache_key.to_string(), &version_dir, progress));
         }
 
-        // Check GitHub for updates if we can
-        if let Some(language_server_id) = language_server_id {
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-            );
+        // Another Zed window may be installing the same version; wait for it rather than
+        // treating its work in progress as incomplete
+        let lock = InstallLock::acquire(self.fs.clone(), &version_dir, fn_name)?;
+        let progress = &lock.heartbeat(progress);
+        if self.is_installed(config, options, &version_dir, fn_name) {
+            logger::Logger::debug(&format!(
+                "{}: {} was installed by another instance",
+                fn_name, version_dir
+            ));
+            return Ok(self.use_version_dir(cache_key.to_string(), &version_dir, progress));
+        }
+        if self.fs.metadata(&version_dir).is_ok() {
+            // Left over from an install that predates staging, or damaged since (antivirus
+            // quarantine, partial cleanup); reinstall the same version from scratch
+            logger::Logger::warn(&format!(
+                "{}: found incomplete or modified directory, reinstalling: {}",
+                fn_name, version_dir
+            ));
+            self.fs.remove_dir_all(&version_dir).ok();
         }
 
-        let github_version = zed::latest_github_release(
-            &config.github_repo,
-            zed::GithubReleaseOptions {
-                require_assets: false,
-                pre_release: false,
-            },
-        )
-        .ok()
-        .map(|release| release.version.trim_start_matches('v').to_string());
-
-        // Use GitHub version if it's newer than local, otherwise use local
-        let version = if let Some(gh_ver) = github_version {
-            if latest_local_version
-                .as_ref()
-                .map_or(true, |local| gh_ver > *local)
-            {
-                gh_ver
-            } else {
-                latest_local_version.unwrap()
+        // Extract into a staging directory that only becomes `version_dir` once validated,
+        // so a crash mid-extract never leaves a half-populated install behind.
+        // Holding the lock makes any existing staging directory a leftover from a crash.
+        let staging_dir = format!(".{}.staging", version_dir);
+        self.fs.remove_dir_all(&staging_dir).ok();
+
+        // Need to install the version, either from the side-loaded archive or by downloading
+        let staged = if let Some(archive_path) = &options.local_archive {
+            self.fs.open(archive_path).map_err(|e| {
+                InstallError::Config(format!(
+                    "failed to read local archive {}: {}. Zed extensions can only read files inside their work directory; copy the archive into {}",
+                    archive_path,
+                    e,
+                    self.work_dir()
+                ))
+            })?;
+            logger::Logger::info(&format!(
+                "{}: installing {} {} from local archive {}",
+                fn_name, config.prefix, version, archive_path
+            ));
+            // The user supplied this file, so there is no published digest to check against
+            progress.report(&InstallStatus::Extracting);
+            let files = self
+                .install_archive(
+                    archive_path,
+                    config.manifest.archive,
+                    archive_path,
+                    &staging_dir,
+                    None,
+                    progress,
+                )
+                .map_err(|e| {
+                    self.fs.remove_dir_all(&staging_dir).ok();
+                    logger::Logger::error(&format!(
+                        "{}: failed to install local archive {}: {}",
+                        fn_name, archive_path, e
+                    ));
+                    e
+                })?;
+            StagedInstall {
+                source: archive_path.clone(),
+                digest: None,
+                files,
             }
+        } else if options.offline {
+            return Err(InstallError::Offline(self.offline_error(
+                config,
+                &format!("{} {} is not installed", config.prefix, version),
+                version,
+            )));
         } else {
-            // No GitHub access, fall back to local version
-            latest_local_version.ok_or_else(|| {
-                format!(
-                    "No {} version found locally and cannot check GitHub for updates",
-                    config.prefix
-                )
-            })?
+            self.download_version(config, version, &staging_dir, fn_name, progress)?
         };
 
-        let version_dir = format!("{}-{}", config.prefix, version);
+        // Pick the binary to run, e.g. the newest framework build the runtimes can run
+        let binary_path =
+            config.choose_binary_path(self.fs.as_ref(), &staging_dir, &options.runtimes);
+        let binary = Self::relative_to(&staging_dir, &binary_path);
+        logger::Logger::debug(&format!("{}: using binary {}", fn_name, binary));
 
-        // If we already have this version locally, validate it's complete
-        if fs::metadata(&version_dir).map_or(false, |stat| stat.is_dir()) {
-            // Check if the expected binary exists to validate the download was complete
-            let binary_path = (config.get_binary_path)(&version_dir);
+        // Check the install is complete and well-formed before it becomes visible
+        let expected_files = config.expected_files(binary);
+        let failures = validation::validate_install(
+            self.fs.as_ref(),
+            &staging_dir,
+            &expected_files,
+            &staged.files,
+        );
+        if !failures.is_empty() {
+            let reasons = failures
+                .iter()
+                .map(|failure| failure.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            logger::Logger::error(&format!(
+                "{}: {} {} failed validation: {}",
+                fn_name, config.prefix, version, reasons
+            ));
+            self.fs.remove_dir_all(&staging_dir).ok();
+            return Err(InstallError::ArchiveCorrupt(format!(
+                "{} {} failed validation: {}",
+                config.prefix, version, reasons
+            )));
+        }
 
-            if fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
-                logger::Logger::debug(&format!(
-                    "{}: validated existing directory: {}",
-                    fn_name, version_dir
-                ));
-                if let Some(language_server_id) = language_server_id {
-                    zed::set_language_server_installation_status(
-                        language_server_id,
-                        &zed::LanguageServerInstallationStatus::None,
-                    );
-                }
-                // Convert to absolute path before caching and returning
-                let absolute_version_dir = path_utils::normalize_path_to_absolute(&version_dir);
-                self.version_dir_cache.insert(config.prefix.clone(), absolute_version_dir.clone());
-                return Ok(absolute_version_dir);
-            } else {
-                // Directory exists but is incomplete/corrupted, clean it up
-                logger::Logger::warn(&format!(
-                    "{}: found incomplete directory, removing: {}",
+        self.mark_executables(&staging_dir, &expected_files, &staged.files)
+            .inspect_err(|_| {
+                self.fs.remove_dir_all(&staging_dir).ok();
+            })?;
+
+        // Record what was installed, for the integrity check before each reuse
+        InstallManifest::create(
+            self.fs.as_ref(),
+            &staging_dir,
+            version,
+            &staged.source,
+            staged.digest.as_ref(),
+            binary,
+            &staged.files,
+        )
+        .and_then(|manifest| manifest.save(self.fs.as_ref(), &staging_dir))
+        .map_err(|e| {
+            self.fs.remove_dir_all(&staging_dir).ok();
+            InstallError::Other(e)
+        })?;
+
+        self.fs.rename(&staging_dir, &version_dir).map_err(|e| {
+            self.fs.remove_dir_all(&staging_dir).ok();
+            InstallError::io(&version_dir, &e)
+        })?;
+
+        logger::Logger::info(&format!(
+            "{}: installed and validated {} ({} files)",
+            fn_name,
+            version_dir,
+            staged.files.len()
+        ));
+
+        // The new version is in place; failing to clean up after it mustn't fail the install
+        if let Err(e) = self.clean_up(config, options, &[version_dir.as_str()]) {
+            logger::Logger::warn(&format!(
+                "{}: failed to clean up after installing {}: {}",
+                fn_name, version_dir, e
+            ));
+        }
+
+        Ok(self.use_version_dir(cache_key.to_string(), &version_dir, progress))
+    }
+
+    /// Clean up the tool's leftovers, and old versions unless a version is pinned: other
+    /// worktrees may pin (or track latest with) a different version we shouldn't pull out
+    /// from under them. A side-loaded version isn't necessarily the newest, so it leaves
+    /// others alone too. The `in_use` version directories, the known-good version and the
+    /// side-loaded archive are kept. Runs after each install, and on demand.
+    pub fn clean_up(
+        &self,
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+        in_use: &[&str],
+    ) -> Result<GcReport, InstallError> {
+        let keep_versions = (options.version.is_none() && options.local_archive.is_none())
+            .then_some(options.retain_versions);
+        let known_good_dir = options
+            .known_good
+            .as_ref()
+            .map(|version| format!("{}-{}", config.prefix, version));
+        let archive_entry = options
+            .local_archive
+            .as_deref()
+            .and_then(|path| self.work_dir_entry(path));
+        let mut protected = in_use.to_vec();
+        protected.extend(known_good_dir.as_deref());
+        protected.extend(archive_entry.as_deref());
+        self.collect_garbage(config, &RetentionPolicy::new(keep_versions), &protected)
+    }
+
+    /// Name of the work directory entry `path` is or lies in; None outside the work directory
+    fn work_dir_entry(&self, path: &str) -> Option<String> {
+        let work_dir = format!("{}/", self.work_dir());
+        let relative = match path.strip_prefix(&work_dir) {
+            Some(relative) => relative,
+            None if Path::new(path).is_absolute() => return None,
+            None => path,
+        };
+        relative
+            .split(['/', '\\'])
+            .find(|part| !part.is_empty() && *part != ".")
+            .map(str::to_string)
+    }
+
+    /// Whether `version_dir` holds a complete install whose files all match its manifest.
+    /// Installs made before manifests existed are adopted as they are.
+    fn is_installed(
+        &self,
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+        version_dir: &str,
+        fn_name: &str,
+    ) -> bool {
+        if !self.fs.is_dir(version_dir) || !self.is_intact(config, version_dir, true, fn_name) {
+            return false;
+        }
+
+        if InstallManifest::load(self.fs.as_ref(), version_dir).is_none() {
+            let version = version_dir
+                .strip_prefix(&format!("{}-", config.prefix))
+                .unwrap_or(version_dir);
+            let binary_path =
+                config.choose_binary_path(self.fs.as_ref(), version_dir, &options.runtimes);
+            let binary = Self::relative_to(version_dir, &binary_path);
+            match InstallManifest::adopt(self.fs.as_ref(), version_dir, version, binary)
+                .and_then(|manifest| manifest.save(self.fs.as_ref(), version_dir))
+            {
+                Ok(()) => logger::Logger::debug(&format!(
+                    "{}: recorded a manifest for existing install {}",
                     fn_name, version_dir
-                ));
-                fs::remove_dir_all(&version_dir).ok();
+                )),
+                Err(e) => logger::Logger::warn(&format!("{}: {}", fn_name, e)),
             }
         }
+        true
+    }
+
+    /// Whether the binary of `version_dir` exists and the install matches its manifest,
+    /// if it has one: sizes only, or full hashes when `hashes` is set
+    fn is_intact(
+        &self,
+        config: &VersionDirConfig,
+        version_dir: &str,
+        hashes: bool,
+        fn_name: &str,
+    ) -> bool {
+        if !self
+            .fs
+            .is_file(&config.binary_path(self.fs.as_ref(), version_dir))
+        {
+            return false;
+        }
 
-        // Need to download new version
-        let platform_str = (config.get_platform_string)()
-            .map_err(|e| format!("{}: failed to determine platform: {}", fn_name, e))?;
+        let Some(manifest) = InstallManifest::load(self.fs.as_ref(), version_dir) else {
+            return true;
+        };
+        let problems = manifest.verify(self.fs.as_ref(), version_dir, hashes);
+        if !problems.is_empty() {
+            logger::Logger::warn(&format!(
+                "{}: {} failed its integrity check: {}",
+                fn_name,
+                version_dir,
+                problems.join("; ")
+            ));
+        }
+        problems.is_empty()
+    }
 
-        // Start download
-        if let Some(language_server_id) = language_server_id {
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
-            );
+    /// Whether a previously resolved binary path can be reused as is: the binary exists,
+    /// and a managed install still matches its manifest
+    pub fn is_reusable(&self, config: &VersionDirConfig, binary_path: &str) -> bool {
+        match Self::installed_version(config, binary_path) {
+            Some(version) => self.is_intact(
+                config,
+                &format!("{}/{}-{}", self.work_dir(), config.prefix, version),
+                false,
+                "is_reusable",
+            ),
+            None => self.fs.is_file(binary_path),
         }
+    }
 
-        // Create the version directory if it doesn't exist
-        fs::create_dir_all(&version_dir)
-            .map_err(|e| format!("failed to create version directory {}: {}", version_dir, e))?;
-        logger::Logger::debug(&format!("{}: created directory: {}", fn_name, version_dir));
+    /// `path` relative to `dir`, which it is inside of
+    fn relative_to<'a>(dir: &str, path: &'a str) -> &'a str {
+        path.strip_prefix(dir)
+            .map_or(path, |relative| relative.trim_start_matches('/'))
+    }
 
-        // Determine download URL using the config's resolver
-        let download_url = (config.get_download_url)(&version, &platform_str)?;
+    /// Settle on `version_dir`: clear the installation status and cache its absolute path
+    fn use_version_dir(
+        &mut self,
+        cache_key: String,
+        version_dir: &str,
+        progress: &dyn InstallProgress,
+    ) -> String {
+        progress.report(&InstallStatus::Ready);
 
-        logger::Logger::debug(&format!("{}: downloading from {}", fn_name, download_url));
+        let mut usage = InstallUsage::load(self.fs.as_ref());
+        usage.touch(version_dir);
+        if let Err(e) = usage.save(self.fs.as_ref()) {
+            logger::Logger::warn(&format!("use_version_dir: {}", e));
+        }
 
-        // Use retry logic to download - handles incomplete downloads and extraction failures
-        Self::download_with_retry(&download_url, &version_dir, 3)?;
+        // Convert to absolute path before caching and returning
+        let absolute_version_dir = self.fs.absolute(version_dir);
+        self.version_dir_cache
+            .insert(cache_key, absolute_version_dir.clone());
+        absolute_version_dir
+    }
 
-        // Poll for the binary to appear (handles antivirus/file locker delays)
-        let binary_path = (config.get_binary_path)(&version_dir);
+    /// Everything in the work directory belonging to the tool: installs with their size,
+    /// version and last use, plus staging leftovers, partial downloads and locks
+    pub fn inventory(&self, config: &VersionDirConfig) -> Result<Vec<WorkDirEntry>, InstallError> {
+        let usage = InstallUsage::load(self.fs.as_ref());
+        let mut inventory = Vec::new();
+        let entries = self
+            .fs
+            .read_dir(".")
+            .map_err(|e| InstallError::io(&self.work_dir(), &e))?;
+        for name in entries {
+            let Ok(stat) = self.fs.metadata(&name) else {
+                continue;
+            };
+            let Some((version_dir, kind)) =
+                WorkDirEntry::classify(&config.prefix, &name, stat.is_dir)
+            else {
+                continue;
+            };
 
-        let max_polls = 50; // Poll up to 50 times
-        let mut poll_count = 0;
-        let mut has_content = fs::metadata(&binary_path).map_or(false, |stat| stat.is_file());
+            let last_used = match kind {
+                maintenance::EntryKind::Install(_) => {
+                    usage.dirs.get(&name).copied().or_else(|| {
+                        InstallManifest::load(self.fs.as_ref(), &name)
+                            .map(|manifest| manifest.installed_at)
+                    })
+                }
+                _ => None,
+            };
+            inventory.push(WorkDirEntry {
+                size: maintenance::disk_usage(self.fs.as_ref(), &name),
+                age: stat.modified.and_then(|modified| modified.elapsed().ok()),
+                name,
+                version_dir,
+                kind,
+                last_used,
+            });
+        }
+        inventory.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(inventory)
+    }
 
-        while !has_content && poll_count < max_polls {
-            poll_count += 1;
-            has_content = fs::metadata(&binary_path).map_or(false, |stat| stat.is_file());
-            if !has_content && poll_count % 5 == 0 {
-                logger::Logger::debug(&format!(
-                    "{}: polling for {} binary... (poll {}/{})",
-                    fn_name, config.binary_name_for_logging, poll_count, max_polls
-                ));
+    /// Remove what `policy` doesn't keep of the tool's work directory entries, never
+    /// touching the `protected` entries, folders the tool's source reads from, or anything
+    /// being installed. See `clean_up` for the policy installs use.
+    pub fn collect_garbage(
+        &self,
+        config: &VersionDirConfig,
+        policy: &RetentionPolicy,
+        protected: &[&str],
+    ) -> Result<GcReport, InstallError> {
+        let fn_name = format!("collect_garbage[{}]", config.prefix);
+        let source_entries: Vec<String> = config
+            .version_source
+            .local_paths()
+            .into_iter()
+            .filter_map(|path| self.work_dir_entry(path))
+            .collect();
+        let mut protected = protected.to_vec();
+        protected.extend(source_entries.iter().map(String::as_str));
+        let inventory = self.inventory(config)?;
+        let removals = policy.removals(&inventory, &protected, maintenance::now());
+
+        let mut report = GcReport::default();
+        for entry in &inventory {
+            if !removals.contains(&entry) {
+                report.retained += entry.size;
+                continue;
+            }
+            let result = if self.fs.is_dir(&entry.name) {
+                self.fs.remove_dir_all(&entry.name)
+            } else {
+                self.fs.remove_file(&entry.name)
+            };
+            match result {
+                Ok(()) => {
+                    logger::Logger::debug(&format!(
+                        "{}: removed {:?} {} ({})",
+                        fn_name,
+                        entry.kind,
+                        entry.name,
+                        maintenance::format_size(entry.size)
+                    ));
+                    report.removed.push(entry.name.clone());
+                    report.freed += entry.size;
+                }
+                Err(e) => {
+                    logger::Logger::warn(&format!(
+                        "{}: failed to remove {}: {}",
+                        fn_name, entry.name, e
+                    ));
+                    report.retained += entry.size;
+                }
             }
         }
 
-        if !has_content {
-            logger::Logger::error(&format!(
-                "{}: {} binary not found at {} after {} polls",
-                fn_name, config.binary_name_for_logging, binary_path, poll_count
-            ));
-            fs::remove_dir_all(&version_dir).ok();
-            return Err(format!(
-                "failed to download {}: binary not found after extraction",
-                config.prefix
+        if !report.removed.is_empty() {
+            let mut usage = InstallUsage::load(self.fs.as_ref());
+            usage
+                .dirs
+                .retain(|dir, _| !report.removed.iter().any(|removed| removed == dir));
+            if let Err(e) = usage.save(self.fs.as_ref()) {
+                logger::Logger::warn(&format!("{}: {}", fn_name, e));
+            }
+        }
+        logger::Logger::info(&format!(
+            "{}: removed {} entries, freed {}, {} remain in use",
+            fn_name,
+            report.removed.len(),
+            maintenance::format_size(report.freed),
+            maintenance::format_size(report.retained)
+        ));
+        Ok(report)
+    }
+
+    /// Installed versions of the tool, newest first, as (parsed, raw) version
+    pub fn installed_versions(&self, config: &VersionDirConfig) -> Vec<(Version, String)> {
+        let prefix = format!("{}-", config.prefix);
+        let mut installed: Vec<(Version, String)> = self
+            .fs
+            .read_dir(".")
+            .into_iter()
+            .flatten()
+            .filter_map(|name| {
+                let raw_version = name.strip_prefix(&prefix)?;
+                self.fs.is_dir(&name).then_some(())?;
+                Some((Version::parse(raw_version).ok()?, raw_version.to_string()))
+            })
+            .collect();
+        installed.sort_by(|a, b| b.0.cmp(&a.0));
+        installed
+    }
+
+    /// Installed version a binary path belongs to, e.g. `0.16.0` for
+    /// `.../csharp-language-server-0.16.0/tools/...`
+    pub fn installed_version(config: &VersionDirConfig, binary_path: &str) -> Option<String> {
+        let prefix = format!("{}-", config.prefix);
+        Path::new(binary_path)
+            .components()
+            .find_map(|component| component.as_os_str().to_str()?.strip_prefix(&prefix))
+            .map(str::to_string)
+    }
+
+    /// Absolute binary path of an installed version, if it is (still) installed
+    pub fn installed_binary_path(
+        &self,
+        config: &VersionDirConfig,
+        version: &str,
+    ) -> Option<String> {
+        let version_dir = self.fs.absolute(&format!("{}-{}", config.prefix, version));
+        let binary_path = config.binary_path(self.fs.as_ref(), &version_dir);
+        self.fs.is_file(&binary_path).then_some(binary_path)
+    }
+
+    /// Download and install `version` into `version_dir`
+    fn download_version(
+        &self,
+        config: &VersionDirConfig,
+        version: &str,
+        version_dir: &str,
+        fn_name: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<StagedInstall, InstallError> {
+        let platform_str = config.platform_string()?;
+        let asset_name = config.archive_name(version, &platform_str);
+        let asset = AssetRequest {
+            tool: &config.prefix,
+            version,
+            platform: &platform_str,
+            name: &asset_name,
+        };
+
+        // Try each source in turn, moving on only when one can't be reached or can't
+        // vouch for the asset
+        let upstream = config.manifest.version_source();
+        let sources: Vec<InstallSource> = config
+            .version_source
+            .alternatives()
+            .into_iter()
+            .flat_map(|source| {
+                let require_digest = config.manifest.require_digest && *source == upstream;
+                self.releases
+                    .release_sources(source)
+                    .into_iter()
+                    .map(move |backend| InstallSource {
+                        backend,
+                        require_digest,
+                    })
+            })
+            .collect();
+        let mut result = Err(InstallError::Other(format!(
+            "no source configured for {}",
+            config.prefix
+        )));
+        for (i, source) in sources.iter().enumerate() {
+            result =
+                self.install_from_source(config, source, &asset, version_dir, fn_name, progress);
+            match &result {
+                Err(e) if e.is_source_failure() && i + 1 < sources.len() => {
+                    logger::Logger::warn(&format!(
+                        "{}: {} failed, trying {}: {}",
+                        fn_name,
+                        source.backend,
+                        sources[i + 1].backend,
+                        e
+                    ));
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// Locate the asset in `source`, and download (or extract) and verify it
+    fn install_from_source(
+        &self,
+        config: &VersionDirConfig,
+        source: &InstallSource,
+        asset: &AssetRequest,
+        version_dir: &str,
+        fn_name: &str,
+        progress: &dyn InstallProgress,
+    ) -> Result<StagedInstall, InstallError> {
+        // Resolve the digest the archive must match before downloading anything
+        let resolved = source.backend.resolve(self.source_host(), asset)?;
+        let location = resolved.location.to_string();
+        if resolved.digest.is_none() {
+            if source.require_digest {
+                return Err(InstallError::Unverified(format!(
+                    "refusing to install {} {}: no published checksum found for {}",
+                    config.prefix, asset.version, location
+                )));
+            }
+            logger::Logger::warn(&format!(
+                "{}: no published checksum for {}, installing unverified",
+                fn_name, location
             ));
         }
 
-        // Validate the binary is a valid Windows PE executable
-        #[cfg(windows)]
+        let files = match &resolved.location {
+            AssetLocation::Remote { url, auth } => {
+                logger::Logger::debug(&format!("{}: downloading from {}", fn_name, url));
+
+                // Use retry logic to download - handles dropped connections and extraction
+                // failures. Retries resume rather than start over, so a flaky connection
+                // gets a few more. The version directory is only created once the archive
+                // has been verified.
+                self.download_with_retry(
+                    url,
+                    config.manifest.archive,
+                    auth.as_ref(),
+                    version_dir,
+                    resolved.digest.as_ref(),
+                    progress,
+                )?
+            }
+            AssetLocation::Local(path) => {
+                logger::Logger::debug(&format!("{}: extracting {}", fn_name, path));
+                progress.report(&InstallStatus::Extracting);
+                self.install_archive(
+                    path,
+                    config.manifest.archive,
+                    path,
+                    version_dir,
+                    resolved.digest.as_ref(),
+                    progress,
+                )?
+            }
+        };
+        Ok(StagedInstall {
+            source: location,
+            digest: resolved.digest,
+            files,
+        })
+    }
+
+    /// Version to install a side-loaded archive as: the pinned version, or failing
+    /// that the version embedded in the archive's file name
+    fn local_archive_version(
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+    ) -> Result<String, InstallError> {
+        let archive_path = options.local_archive.as_deref().unwrap_or_default();
+        if let Some(pinned) = options.version.as_ref().and_then(VersionReq::exact) {
+            return Ok(pinned.to_string());
+        }
+
+        let version = release_source::version_from_archive_name(archive_path).ok_or_else(|| {
+            InstallError::Config(format!(
+                "cannot tell which {} version {} contains; set {}.version to the exact version",
+                config.prefix, archive_path, config.settings_key
+            ))
+        })?;
+        if let Some(requirement) = &options.version {
+            let parsed = Version::parse(&version)?;
+            if !requirement.matches(&parsed) {
+                return Err(InstallError::Config(format!(
+                    "local archive {} is {} {}, which doesn't match '{}'",
+                    archive_path, config.prefix, version, requirement
+                )));
+            }
+        }
+        Ok(version)
+    }
+
+    /// Extension work directory, where installs live and side-loaded archives must be placed
+    fn work_dir(&self) -> String {
+        self.fs.absolute("").trim_end_matches('/').to_string()
+    }
+
+    /// Explain what offline mode is missing and how to provide it
+    fn offline_error(&self, config: &VersionDirConfig, problem: &str, version: &str) -> String {
+        let platform = config
+            .platform_string()
+            .unwrap_or_else(|_| "<platform>".to_string());
+        let work_dir = self.work_dir();
+        format!(
+            "offline mode: {}. Either extract it so that {} exists, or place {} in {} and set {}.archive to its file name",
+            problem,
+            config.binary_path(
+                self.fs.as_ref(),
+                &format!("{}/{}-{}", work_dir, config.prefix, version)
+            ),
+            config.archive_name(version, &platform),
+            work_dir,
+            config.settings_key
+        )
+    }
+
+    /// Like `latest_remote_version`, but answered from the persisted update state when the
+    /// last check is younger than `options.update_check_interval`
+    fn throttled_remote_version(
+        &self,
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+        fn_name: &str,
+        progress: &dyn InstallProgress,
+    ) -> Option<(Version, String)> {
+        if options.offline {
+            return None;
+        }
+
+        let source = config.version_source.to_string();
+        let mut state = UpdateState::load(self.fs.as_ref());
+        if let Some(raw_version) =
+            state.fresh_version(&config.prefix, &source, options.update_check_interval)
         {
-            if let Ok(metadata) = fs::metadata(&binary_path) {
-                let file_size = metadata.len();
+            if let Ok(version) = Version::parse(raw_version) {
                 logger::Logger::debug(&format!(
-                    "{}: {} binary size: {} bytes",
-                    fn_name, config.binary_name_for_logging, file_size
+                    "{}: skipping update check, {} was checked recently (latest {})",
+                    fn_name, source, raw_version
                 ));
+                return Some((version, raw_version.to_string()));
+            }
+        }
 
-                // Check if file is large enough to be a valid PE executable (min ~100KB)
-                if file_size < 100_000 {
-                    logger::Logger::error(&format!(
-                        "{}: {} binary appears too small: {} bytes",
-                        fn_name, config.binary_name_for_logging, file_size
-                    ));
-                    // Don't fail yet, it might still work
-                }
+        let remote_version = self.latest_remote_version(config, options, fn_name, progress);
+        // Only successful checks are recorded, so a network outage doesn't suppress the next try
+        if let Some((_, raw_version)) = &remote_version {
+            state.record(&config.prefix, &source, raw_version);
+            if let Err(e) = state.save(self.fs.as_ref()) {
+                logger::Logger::warn(&format!("{}: {}", fn_name, e));
+            }
+        }
+        remote_version
+    }
 
-                // Try to read first few bytes to check for PE signature
-                if let Ok(mut file) = fs::File::open(&binary_path) {
-                    use std::io::Read;
-                    let mut header = [0u8; 2];
-                    if file.read_exact(&mut header).is_ok() {
-                        if header == [0x4d, 0x5a] {
-                            // "MZ" - DOS header for PE files
-                            logger::Logger::debug(&format!(
-                                "{}: {} binary has valid PE header",
-                                fn_name, config.binary_name_for_logging
-                            ));
-                        } else {
-                            logger::Logger::error(&format!(
-                                "{}: {} binary has invalid header: {:02x}{:02x}",
-                                fn_name, config.binary_name_for_logging, header[0], header[1]
-                            ));
-                            fs::remove_dir_all(&version_dir).ok();
-                            return Err(format!(
-                                "{} binary has invalid PE header signature",
-                                config.binary_name_for_logging
-                            ));
-                        }
-                    }
-                }
+    /// Newest remote version of the tool that satisfies the version requirement,
+    /// as (parsed, raw) version
+    fn latest_remote_version(
+        &self,
+        config: &VersionDirConfig,
+        options: &InstallOptions,
+        fn_name: &str,
+        progress: &dyn InstallProgress,
+    ) -> Option<(Version, String)> {
+        if options.offline {
+            return None;
+        }
+
+        // Check for updates if we can
+        progress.report(&InstallStatus::CheckingForUpdate);
+
+        let query = VersionQuery {
+            requirement: options.version.as_ref(),
+            runtimes: &options.runtimes,
+        };
+        for source in self.releases.release_sources(&config.version_source) {
+            match source.newest_version(self.source_host(), &query) {
+                Ok(version) => return version,
+                Err(e) => logger::Logger::warn(&format!(
+                    "{}: failed to look up versions in {}: {}",
+                    fn_name, source, e
+                )),
             }
         }
+        None
+    }
+}
 
-        logger::Logger::debug(&format!(
-            "{}: successfully downloaded and extracted to {} (polls: {})",
-            fn_name, version_dir, poll_count
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::DigestAlgorithm;
+    use crate::file_system::MemoryFileSystem;
+    use crate::http::FakeHttpClient;
+    use crate::progress::ToolProgress;
+    use crate::release_source::FakeReleases;
+    use crate::tool_manifest::{SourceSpec, ToolManifest, ValidationRule};
+    use crate::version_config::VersionSource;
+    use std::collections::BTreeMap;
+
+    const TOOL: &str = "fake-tool";
+    const PROGRESS: ToolProgress = ToolProgress { tool: TOOL };
+
+    /// A platform-independent tool published as `fake-tool-<version>.tar.gz`
+    fn fake_tool() -> VersionDirConfig {
+        let manifest = ToolManifest {
+            name: TOOL.to_string(),
+            source: SourceSpec::Url {
+                url: "https://releases.example".to_string(),
+                template: None,
+            },
+            assets: BTreeMap::from([("any".to_string(), "fake-tool-{version}.tar.gz".to_string())]),
+            archive: None,
+            entry_point: "bin/fake-tool".to_string(),
+            validate: vec![ValidationRule {
+                path: "{entry_point}".to_string(),
+                format: FileFormat::Any,
+            }],
+            require_digest: false,
+        };
+        VersionDirConfig::new(manifest, "fake_tool")
+    }
+
+    /// A tar.gz of `(path, mode, contents)` entries. Paths are written as they are, so
+    /// archives can hold the `..` and absolute paths `append_data` refuses.
+    fn tar_gz(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
         ));
+        for (path, mode, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(contents.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            builder.append(&header, *contents).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
 
-        // Clean up old versions
-        let entries =
-            fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("failed to load directory entry {e}"))?;
-            if let Some(name) = entry.file_name().to_str() {
-                if name.starts_with(&format!("{}-", config.prefix)) && name != version_dir {
-                    fs::remove_dir_all(entry.path()).ok();
-                }
+    fn release_archive(version: &str) -> Vec<u8> {
+        let binary = format!("fake-tool {}", version);
+        tar_gz(&[
+            ("bin/fake-tool", 0o755, binary.as_bytes()),
+            ("README.md", 0o644, b"fake tool"),
+        ])
+    }
+
+    fn asset_url(version: &str) -> String {
+        FakeReleases::asset_url(version, &format!("fake-tool-{}.tar.gz", version))
+    }
+
+    fn sha256(data: &[u8]) -> ExpectedDigest {
+        ExpectedDigest {
+            algorithm: DigestAlgorithm::Sha256,
+            value: DigestAlgorithm::Sha256.compute_reader(data).unwrap(),
+            source: "fake-tool.sha256".to_string(),
+            size: Some(data.len() as u64),
+        }
+    }
+
+    /// The work directory, network and releases a manager runs against
+    #[derive(Clone)]
+    struct Host {
+        fs: MemoryFileSystem,
+        http: FakeHttpClient,
+        releases: FakeReleases,
+    }
+
+    impl Host {
+        fn new() -> Self {
+            Self {
+                fs: MemoryFileSystem::new(),
+                http: FakeHttpClient::new(),
+                releases: FakeReleases::new(&[]),
             }
         }
 
-        if let Some(language_server_id) = language_server_id {
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::None,
-            );
+        /// Publish `version` with its digest, without serving the archive
+        fn publish(&self, version: &str) {
+            self.releases.publish(version);
+            self.releases
+                .publish_digest(&asset_url(version), sha256(&release_archive(version)));
         }
 
-        // Convert to absolute path before caching and returning
-        let absolute_version_dir = path_utils::normalize_path_to_absolute(&version_dir);
-        self.version_dir_cache.insert(config.prefix.clone(), absolute_version_dir.clone());
-        Ok(absolute_version_dir)
+        /// Publish and serve `version`
+        fn release(&self, version: &str) {
+            self.publish(version);
+            self.http
+                .serve(&asset_url(version), &release_archive(version));
+        }
+
+        fn manager(&self) -> BinaryManager {
+            BinaryManager::with_host(self.fs.clone(), self.http.clone(), self.releases.clone())
+        }
+
+        /// Work directory entries of the tool, besides its installs' contents
+        fn entries(&self) -> Vec<String> {
+            let mut entries = self.fs.read_dir(".").unwrap();
+            entries.retain(|name| name.contains(TOOL));
+            entries.sort();
+            entries
+        }
+    }
+
+    fn install(
+        manager: &mut BinaryManager,
+        options: &InstallOptions,
+    ) -> Result<String, InstallError> {
+        manager.get_version_dir(&fake_tool(), options, &PROGRESS)
+    }
+
+    #[test]
+    fn records_executable_tar_entries() {
+        let archive = tar_gz(&[
+            ("netcoredbg/netcoredbg", 0o755, b"data"),
+            ("netcoredbg/ManagedPart.dll", 0o644, b"data"),
+        ]);
+
+        let host = Host::new();
+        let extracted = host
+            .manager()
+            .extract_tar_gz(archive.as_slice(), "extract-tar", &PROGRESS)
+            .unwrap();
+
+        let executables: Vec<&str> = extracted
+            .iter()
+            .filter(|file| file.executable)
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(executables, vec!["netcoredbg/netcoredbg"]);
+        assert_eq!(
+            host.fs.read("extract-tar/netcoredbg/netcoredbg").unwrap(),
+            b"data"
+        );
+    }
+
+    #[test]
+    fn keeps_tar_entries_inside_the_destination() {
+        let enclosed = |path| BinaryManager::enclosed_path(Path::new(path));
+        assert_eq!(enclosed("bin/./tool"), Some(PathBuf::from("bin/tool")));
+        assert_eq!(enclosed("bin/../tool"), Some(PathBuf::from("tool")));
+        assert_eq!(enclosed("../tool"), None);
+        assert_eq!(enclosed("bin/../../tool"), None);
+        assert_eq!(enclosed("/etc/passwd"), None);
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "bin/passwd", "/etc/passwd")
+            .unwrap();
+        let links = builder.into_inner().unwrap();
+        let host = Host::new();
+        let extracted = host
+            .manager()
+            .extract_tar_gz(
+                flate2::read::GzEncoder::new(links.as_slice(), flate2::Compression::fast()),
+                "links",
+                &PROGRESS,
+            )
+            .unwrap();
+        assert!(extracted.is_empty());
+        assert!(!host.fs.exists("links/bin/passwd"));
+
+        let archive = tar_gz(&[
+            ("../escaped", 0o644, b"data"),
+            ("bin/../../escaped", 0o644, b"data"),
+            ("/escaped", 0o644, b"data"),
+            ("bin/tool", 0o755, b"data"),
+        ]);
+        let extracted = host
+            .manager()
+            .extract_tar_gz(archive.as_slice(), "extract/tool", &PROGRESS)
+            .unwrap();
+        let paths: Vec<&str> = extracted.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["bin/tool"]);
+        assert_eq!(host.fs.read_dir("extract").unwrap(), vec!["tool"]);
+        assert!(!host.fs.exists("escaped") && !host.fs.exists("extract/escaped"));
+    }
+
+    #[test]
+    fn detects_archive_formats() {
+        let detect = |name, declared, data: &[u8]| ArchiveFormat::detect(name, declared, data);
+        let unknown = b"data".as_slice();
+        assert_eq!(
+            detect("netcoredbg-linux-x64.tar.gz", None, unknown),
+            Ok(ArchiveFormat::TarGz)
+        );
+        assert_eq!(detect("tool.TGZ", None, unknown), Ok(ArchiveFormat::TarGz));
+        assert_eq!(
+            detect("netcoredbg-win-x64.zip", None, unknown),
+            Ok(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect("csharp-ls.0.16.0.nupkg", None, unknown),
+            Ok(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect("https://mirror.example/tool.zip?token=1", None, unknown),
+            Ok(ArchiveFormat::Zip)
+        );
+
+        // Without an extension, the manifest's declaration and then the contents decide
+        assert_eq!(
+            detect(
+                "https://feed.example/download",
+                Some(ArchiveKind::TarGz),
+                unknown
+            ),
+            Ok(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            detect("download", None, b"PK\x03\x04"),
+            Ok(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect("download", None, &[0x1f, 0x8b, 8, 0]),
+            Ok(ArchiveFormat::TarGz)
+        );
+        assert!(matches!(
+            detect("download", None, unknown),
+            Err(InstallError::UnexpectedContent { .. })
+        ));
+    }
+
+    #[test]
+    fn extracts_archive_files() {
+        let host = Host::new();
+        host.fs.put(
+            "download.partial",
+            &tar_gz(&[("netcoredbg/netcoredbg", 0o755, b"data")]),
+        );
+
+        // Without an extension on the URL, the file's first bytes pick the extractor
+        let extracted = host
+            .manager()
+            .extract_archive(
+                "https://feed.example/download",
+                None,
+                "download.partial",
+                "extract",
+                &PROGRESS,
+            )
+            .unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(
+            host.fs.read("extract/netcoredbg/netcoredbg").unwrap(),
+            b"data"
+        );
+    }
+
+    #[test]
+    fn extracts_zip_archives() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .unix_permissions(0o755);
+        zip.start_file("netcoredbg/netcoredbg", options).unwrap();
+        zip.write_all(b"data").unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+
+        let host = Host::new();
+        host.fs.put("netcoredbg-win-x64.zip", &archive);
+        let extracted = host
+            .manager()
+            .extract_archive(
+                "netcoredbg-win-x64.zip",
+                None,
+                "netcoredbg-win-x64.zip",
+                "extract",
+                &PROGRESS,
+            )
+            .unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert!(extracted[0].executable);
+        assert_eq!(
+            host.fs.read("extract/netcoredbg/netcoredbg").unwrap(),
+            b"data"
+        );
+    }
+
+    #[test]
+    fn installs_fresh_versions() {
+        let host = Host::new();
+        host.release("1.0.0");
+        let version_dir = install(&mut host.manager(), &InstallOptions::default()).unwrap();
+
+        assert_eq!(version_dir, "/work/fake-tool-1.0.0");
+        assert_eq!(
+            host.fs.read("fake-tool-1.0.0/bin/fake-tool").unwrap(),
+            b"fake-tool 1.0.0"
+        );
+        assert!(host.fs.is_executable("fake-tool-1.0.0/bin/fake-tool"));
+        assert!(!host.fs.is_executable("fake-tool-1.0.0/README.md"));
+        let manifest = InstallManifest::load(&host.fs, "fake-tool-1.0.0").unwrap();
+        assert_eq!(manifest.binary.as_deref(), Some("bin/fake-tool"));
+        assert_eq!(manifest.source, asset_url("1.0.0"));
+
+        // No staging directory, partial download or lock is left behind
+        assert_eq!(host.entries(), vec!["fake-tool-1.0.0"]);
+        assert_eq!(host.http.requests(), vec![asset_url("1.0.0")]);
+    }
+
+    #[test]
+    fn reuses_installed_versions() {
+        let host = Host::new();
+        host.release("1.0.0");
+        let mut manager = host.manager();
+        let options = InstallOptions::default();
+        let version_dir = install(&mut manager, &options).unwrap();
+
+        assert_eq!(install(&mut manager, &options).unwrap(), version_dir);
+        // A new session finds the install on disk instead of in its cache
+        assert_eq!(install(&mut host.manager(), &options).unwrap(), version_dir);
+        assert_eq!(host.http.requests().len(), 1);
+        assert!(manager.is_reusable(&fake_tool(), "/work/fake-tool-1.0.0/bin/fake-tool"));
+
+        // Offline, without the releases, the install still serves
+        host.releases.set_unreachable(true);
+        let offline = InstallOptions {
+            offline: true,
+            ..Default::default()
+        };
+        assert_eq!(install(&mut host.manager(), &offline).unwrap(), version_dir);
+        assert_eq!(host.http.requests().len(), 1);
+    }
+
+    #[test]
+    fn reinstalls_modified_installs() {
+        let host = Host::new();
+        host.release("1.0.0");
+        let mut manager = host.manager();
+        install(&mut manager, &InstallOptions::default()).unwrap();
+
+        host.fs.put("fake-tool-1.0.0/bin/fake-tool", b"truncated");
+        assert!(!manager.is_reusable(&fake_tool(), "/work/fake-tool-1.0.0/bin/fake-tool"));
+        install(&mut manager, &InstallOptions::default()).unwrap();
+
+        assert_eq!(
+            host.fs.read("fake-tool-1.0.0/bin/fake-tool").unwrap(),
+            b"fake-tool 1.0.0"
+        );
+        assert_eq!(host.http.requests().len(), 2);
+    }
+
+    /// Record an update check that found `version`, as if it had just happened
+    fn record_update_check(host: &Host, version: &str) {
+        let mut state = UpdateState::load(&host.fs);
+        state.record(TOOL, &fake_tool().version_source.to_string(), version);
+        state.save(&host.fs).unwrap();
+    }
+
+    #[test]
+    fn ignores_recent_update_checks_offline() {
+        let host = Host::new();
+        host.release("1.0.0");
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+
+        // 1.1.0 came out and was seen, but never installed
+        host.publish("1.1.0");
+        record_update_check(&host, "1.1.0");
+        let offline = InstallOptions {
+            offline: true,
+            update_check_interval: Duration::from_secs(24 * 60 * 60),
+            ..Default::default()
+        };
+        assert_eq!(
+            install(&mut host.manager(), &offline).unwrap(),
+            "/work/fake-tool-1.0.0"
+        );
+        assert_eq!(host.http.requests().len(), 1);
+    }
+
+    #[test]
+    fn keeps_the_installed_version_when_an_upgrade_fails() {
+        let host = Host::new();
+        host.release("1.0.0");
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+
+        // A recent check found 1.1.0, and the releases can't be reached to download it
+        host.publish("1.1.0");
+        record_update_check(&host, "1.1.0");
+        host.releases.set_unreachable(true);
+        let options = InstallOptions {
+            update_check_interval: Duration::from_secs(24 * 60 * 60),
+            ..Default::default()
+        };
+        assert_eq!(
+            install(&mut host.manager(), &options).unwrap(),
+            "/work/fake-tool-1.0.0"
+        );
+
+        // 1.1.0 is reachable but its download fails
+        host.releases.set_unreachable(false);
+        host.http.fail(&asset_url("1.1.0"), "status code 404");
+        assert_eq!(
+            install(&mut host.manager(), &InstallOptions::default()).unwrap(),
+            "/work/fake-tool-1.0.0"
+        );
+        assert_eq!(
+            host.http.requests(),
+            vec![asset_url("1.0.0"), asset_url("1.1.0")]
+        );
+        assert_eq!(host.entries(), vec!["fake-tool-1.0.0"]);
+    }
+
+    #[test]
+    fn requires_digests_only_from_the_upstream_source() {
+        // The release publishes no digest, and the manifest demands one
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http
+            .serve(&asset_url("1.0.0"), &release_archive("1.0.0"));
+        let mut upstream = fake_tool();
+        upstream.manifest.require_digest = true;
+        let options = InstallOptions::default();
+
+        let error = host
+            .manager()
+            .get_version_dir(&upstream, &options, &PROGRESS)
+            .unwrap_err();
+        assert!(matches!(error, InstallError::Unverified(_)), "{}", error);
+        assert!(host.http.requests().is_empty());
+
+        // A mirror without checksums is trusted, and is moved on to when upstream refuses
+        let mirror = VersionSource::HttpDirectory {
+            base_url: "https://mirror.example".to_string(),
+            url_template: None,
+            auth: None,
+        };
+        let source = VersionSource::Fallback {
+            primary: Box::new(upstream.version_source.clone()),
+            fallback: Box::new(mirror),
+        };
+        let mut config = fake_tool().with_source(source);
+        config.manifest.require_digest = true;
+        assert_eq!(
+            host.manager()
+                .get_version_dir(&config, &options, &PROGRESS)
+                .unwrap(),
+            "/work/fake-tool-1.0.0"
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_archives() {
+        // The served archive doesn't match its published digest
+        let host = Host::new();
+        host.publish("1.0.0");
+        host.http
+            .serve(&asset_url("1.0.0"), &release_archive("6.6.6"));
+        let error = install(&mut host.manager(), &InstallOptions::default()).unwrap_err();
+        assert!(
+            matches!(error, InstallError::ChecksumMismatch(_)),
+            "{}",
+            error
+        );
+        // Downloading again would only fetch the same bytes
+        assert_eq!(host.http.requests().len(), 1);
+        assert!(host.entries().is_empty());
+
+        // Without a digest, the archive itself is found to be broken
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http
+            .serve(&asset_url("1.0.0"), b"\x1f\x8bnot really gzip");
+        let error = install(&mut host.manager(), &InstallOptions::default()).unwrap_err();
+        assert!(
+            matches!(error, InstallError::ArchiveCorrupt(_)),
+            "{}",
+            error
+        );
+        assert!(host.entries().is_empty());
+
+        // An archive without the entry point fails validation
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http.serve(
+            &asset_url("1.0.0"),
+            &tar_gz(&[("README.md", 0o644, b"fake tool")]),
+        );
+        let error = install(&mut host.manager(), &InstallOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("failed validation"), "{}", error);
+        assert_eq!(host.http.requests().len(), 1);
+        assert!(host.entries().is_empty());
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let host = Host::new();
+        host.publish("1.0.0");
+        host.http.fail(&asset_url("1.0.0"), "status code 503");
+        let error = install(&mut host.manager(), &InstallOptions::default()).unwrap_err();
+
+        assert_eq!(
+            error,
+            InstallError::HttpStatus {
+                url: asset_url("1.0.0"),
+                status: 503
+            }
+        );
+        assert_eq!(host.http.requests().len(), DOWNLOAD_ATTEMPTS as usize);
+        // Backing off 1, 2, 4 and 8 seconds between the attempts
+        assert_eq!(host.http.waited(), Duration::from_secs(15));
+        assert!(host.entries().is_empty());
+
+        // Permanent failures aren't retried
+        let host = Host::new();
+        host.publish("1.0.0");
+        host.http.fail(&asset_url("1.0.0"), "status code 404");
+        install(&mut host.manager(), &InstallOptions::default()).unwrap_err();
+        assert_eq!(host.http.requests().len(), 1);
+        assert_eq!(host.http.waited(), Duration::ZERO);
+    }
+
+    #[test]
+    fn resumes_interrupted_downloads() {
+        let host = Host::new();
+        host.publish("1.0.0");
+        let archive = release_archive("1.0.0");
+        let url = asset_url("1.0.0");
+        host.http.truncate(&url, &archive, 20);
+        host.http.serve(&url, &archive);
+
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+        assert_eq!(host.http.requests(), vec![url.clone(), url.clone(), url]);
+        assert_eq!(host.http.header(0, "Range"), None);
+        assert_eq!(host.http.header(1, "Range").as_deref(), Some("bytes=19-19"));
+        assert_eq!(host.http.header(2, "Range").as_deref(), Some("bytes=20-"));
+        assert!(host.fs.is_file("fake-tool-1.0.0/bin/fake-tool"));
+    }
+
+    #[test]
+    fn resumes_downloads_after_a_crash() {
+        const PARTIAL: &str = ".fake-tool-1.0.0.staging.partial";
+        let archive = release_archive("1.0.0");
+        let url = asset_url("1.0.0");
+        // Without a published digest the full size is only known from the server
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http.serve(&url, &archive);
+
+        // Killed halfway through the download
+        host.fs.put(PARTIAL, &archive[..20]);
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+        assert_eq!(host.http.header(0, "Range").as_deref(), Some("bytes=19-19"));
+        assert_eq!(host.http.header(1, "Range").as_deref(), Some("bytes=20-"));
+        assert!(host.fs.is_file("fake-tool-1.0.0/bin/fake-tool"));
+        assert!(!host.fs.exists(PARTIAL));
+
+        // Killed after the last byte arrived, before extracting
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http.serve(&url, &archive);
+        host.fs.put(PARTIAL, &archive);
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+        assert_eq!(host.http.requests(), vec![url.clone()]);
+        assert!(host.fs.is_file("fake-tool-1.0.0/bin/fake-tool"));
+    }
+
+    #[test]
+    fn starts_over_when_downloads_cant_resume() {
+        const PARTIAL: &str = ".fake-tool-1.0.0.staging.partial";
+        let archive = release_archive("1.0.0");
+        let url = asset_url("1.0.0");
+
+        // The partial download is longer than the file now served (416)
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http.serve(&url, &archive);
+        host.fs.put(PARTIAL, &vec![0; archive.len() + 10]);
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+        assert_eq!(host.http.requests(), vec![url.clone(), url.clone()]);
+        assert_eq!(host.http.header(1, "Range"), None);
+        assert!(host.fs.is_file("fake-tool-1.0.0/bin/fake-tool"));
+
+        // The server ignores ranges, so the probe already brings the whole file
+        let host = Host::new();
+        host.releases.publish("1.0.0");
+        host.http.serve(&url, &archive);
+        host.http.ignore_ranges();
+        host.fs.put(PARTIAL, b"not the archive's first bytes");
+        install(&mut host.manager(), &InstallOptions::default()).unwrap();
+        assert_eq!(host.http.requests(), vec![url]);
+        assert!(host.fs.is_file("fake-tool-1.0.0/bin/fake-tool"));
+    }
+
+    #[test]
+    fn upgrades_and_cleans_up_old_versions() {
+        let host = Host::new();
+        host.release("1.0.0");
+        let options = InstallOptions {
+            retain_versions: 1,
+            ..Default::default()
+        };
+        install(&mut host.manager(), &options).unwrap();
+
+        host.release("1.1.0");
+        assert_eq!(
+            install(&mut host.manager(), &options).unwrap(),
+            "/work/fake-tool-1.1.0"
+        );
+        assert_eq!(host.entries(), vec!["fake-tool-1.0.0", "fake-tool-1.1.0"]);
+
+        // Leftovers of a crashed install go with the next cleanup
+        host.fs
+            .put(".fake-tool-1.1.5.staging/bin/fake-tool", b"partial");
+        host.release("1.2.0");
+        assert_eq!(
+            install(&mut host.manager(), &options).unwrap(),
+            "/work/fake-tool-1.2.0"
+        );
+        assert_eq!(host.entries(), vec!["fake-tool-1.1.0", "fake-tool-1.2.0"]);
+        assert_eq!(
+            host.manager()
+                .installed_versions(&fake_tool())
+                .into_iter()
+                .map(|(_, raw)| raw)
+                .collect::<Vec<_>>(),
+            vec!["1.2.0", "1.1.0"]
+        );
+
+        // A pinned older version is installed without removing the newer ones
+        let pinned = InstallOptions {
+            version: Some(VersionReq::parse("1.0.0").unwrap()),
+            ..options
+        };
+        install(&mut host.manager(), &pinned).unwrap();
+        assert_eq!(
+            host.entries(),
+            vec!["fake-tool-1.0.0", "fake-tool-1.1.0", "fake-tool-1.2.0"]
+        );
+    }
+
+    #[test]
+    fn cleans_up_on_demand_without_touching_sources() {
+        let host = Host::new();
+        let retain = InstallOptions {
+            retain_versions: 1,
+            ..Default::default()
+        };
+        host.release("1.0.0");
+        install(&mut host.manager(), &retain).unwrap();
+        host.release("1.1.0");
+        install(&mut host.manager(), &retain).unwrap();
+
+        host.fs
+            .put(".fake-tool-1.2.0.staging/bin/fake-tool", b"partial");
+        host.fs
+            .put("fake-tool-mirror/fake-tool-1.3.0.tar.gz", b"archive");
+        host.fs.put("fake-tool_old/bin/fake-tool", b"old");
+        host.fs
+            .put("fake-tool-0.9.0/fake-tool-0.9.0.tar.gz", b"archive");
+        // A folder named like an install, but read from by the configured source
+        let config = fake_tool().with_source(VersionSource::Directory {
+            path: "/work/fake-tool-0.9.0".to_string(),
+        });
+        let options = InstallOptions {
+            retain_versions: 0,
+            ..Default::default()
+        };
+
+        let report = host
+            .manager()
+            .clean_up(&config, &options, &["fake-tool-1.1.0"])
+            .unwrap();
+        assert_eq!(
+            report.removed,
+            vec![".fake-tool-1.2.0.staging", "fake-tool-1.0.0"]
+        );
+        assert_eq!(
+            host.entries(),
+            vec![
+                "fake-tool-0.9.0",
+                "fake-tool-1.1.0",
+                "fake-tool-mirror",
+                "fake-tool_old"
+            ]
+        );
     }
 }
@@ -0,0 +1,292 @@
+use sha2::{Digest, Sha256, Sha512};
+use std::io::Read;
+use zed_extension_api::Result;
+
+use crate::base64;
+use crate::file_system::FileSystem;
+
+/// Hash algorithms that release feeds publish digests in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// GitHub `.sha256` sidecars and `SHA256SUMS` style files
+    Sha256,
+    /// NuGet `packageHash` values
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn digest_len(self) -> usize {
+        match self {
+            DigestAlgorithm::Sha256 => 32,
+            DigestAlgorithm::Sha512 => 64,
+        }
+    }
+
+    /// Lowercase name, as used in `<algorithm>:<hex>` digest strings
+    pub fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Hash everything `reader` yields, without holding it in memory
+    pub fn compute_reader(self, mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+        fn hash<D: Digest>(mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+            let mut hasher = D::new();
+            let mut buffer = [0u8; 64 * 1024];
+            loop {
+                match reader.read(&mut buffer)? {
+                    0 => return Ok(hasher.finalize().to_vec()),
+                    read => hasher.update(&buffer[..read]),
+                }
+            }
+        }
+
+        match self {
+            DigestAlgorithm::Sha256 => hash::<Sha256>(&mut reader),
+            DigestAlgorithm::Sha512 => hash::<Sha512>(&mut reader),
+        }
+    }
+}
+
+/// Digest a downloaded archive is expected to match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedDigest {
+    pub algorithm: DigestAlgorithm,
+    pub value: Vec<u8>,
+    /// Where the digest came from, for error messages (e.g. "netcoredbg-linux-x64.tar.gz.sha256")
+    pub source: String,
+    /// Size of the archive in bytes, when the source publishes it alongside the digest
+    pub size: Option<u64>,
+}
+
+impl ExpectedDigest {
+    /// Build a digest from a hex string (case-insensitive)
+    pub fn from_hex(algorithm: DigestAlgorithm, hex: &str, source: &str) -> Result<Self> {
+        let hex = hex.trim();
+        if !hex.is_ascii() || hex.len() != algorithm.digest_len() * 2 {
+            return Err(format!(
+                "invalid {:?} digest from {}: expected {} hex characters, got {}",
+                algorithm,
+                source,
+                algorithm.digest_len() * 2,
+                hex.len()
+            ));
+        }
+
+        let value = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|e| format!("invalid {:?} digest from {}: {}", algorithm, source, e))?;
+
+        Ok(Self {
+            algorithm,
+            value,
+            source: source.to_string(),
+            size: None,
+        })
+    }
+
+    /// Build a digest from a standard base64 string, as published in NuGet catalog entries
+    pub fn from_base64(algorithm: DigestAlgorithm, encoded: &str, source: &str) -> Result<Self> {
+        let value = base64::decode(encoded.trim())
+            .ok_or_else(|| format!("invalid base64 {:?} digest from {}", algorithm, source))?;
+
+        if value.len() != algorithm.digest_len() {
+            return Err(format!(
+                "invalid {:?} digest from {}: expected {} bytes, got {}",
+                algorithm,
+                source,
+                algorithm.digest_len(),
+                value.len()
+            ));
+        }
+
+        Ok(Self {
+            algorithm,
+            value,
+            source: source.to_string(),
+            size: None,
+        })
+    }
+
+    /// Find the digest for `asset_name` in a checksum file.
+    ///
+    /// Accepts both a bare digest (single `.sha256` sidecar) and `sha256sum` output
+    /// (`<hex>  <file name>` per line, with an optional `*` binary marker).
+    pub fn from_checksum_file(
+        algorithm: DigestAlgorithm,
+        contents: &str,
+        asset_name: &str,
+        source: &str,
+    ) -> Result<Self> {
+        for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut parts = line.split_whitespace();
+            let hex = parts.next().unwrap_or_default();
+            match parts.next() {
+                None => return Self::from_hex(algorithm, hex, source),
+                Some(name) if name.trim_start_matches('*') == asset_name => {
+                    return Self::from_hex(algorithm, hex, source);
+                }
+                Some(_) => continue,
+            }
+        }
+
+        Err(format!(
+            "no checksum for {} found in {}",
+            asset_name, source
+        ))
+    }
+
+    /// Record the published size of the archive
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Verify the file at `path` against this digest, checking the size first when known
+    pub fn verify_file(&self, fs: &dyn FileSystem, path: &str) -> Result<()> {
+        let file = fs
+            .open(path)
+            .map_err(|e| format!("failed to open {}: {}", path, e))?;
+        if let Some(expected_size) = self.size {
+            let actual_size = fs
+                .metadata(path)
+                .map_err(|e| format!("failed to read size of {}: {}", path, e))?
+                .len;
+            if actual_size != expected_size {
+                return Err(format!(
+                    "size mismatch: downloaded archive is {} bytes, but {} expects {}",
+                    actual_size, self.source, expected_size
+                ));
+            }
+        }
+
+        let actual = self
+            .algorithm
+            .compute_reader(file)
+            .map_err(|e| format!("failed to read {}: {}", path, e))?;
+        if actual == self.value {
+            return Ok(());
+        }
+
+        Err(format!(
+            "checksum mismatch: {:?} of downloaded archive is {}, but {} expects {}",
+            self.algorithm,
+            to_hex(&actual),
+            self.source,
+            to_hex(&self.value)
+        ))
+    }
+}
+
+/// Lowercase hex encoding
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn parses_hex_digests() {
+        let digest =
+            ExpectedDigest::from_hex(DigestAlgorithm::Sha256, HELLO_SHA256, "hello.sha256")
+                .unwrap();
+        assert_eq!(to_hex(&digest.value), HELLO_SHA256);
+        let upper = ExpectedDigest::from_hex(
+            DigestAlgorithm::Sha256,
+            &format!(" {}\n", HELLO_SHA256.to_uppercase()),
+            "hello.sha256",
+        )
+        .unwrap();
+        assert_eq!(upper.value, digest.value);
+
+        // A SHA-256 where a SHA-512 belongs, and hex that isn't
+        assert!(ExpectedDigest::from_hex(DigestAlgorithm::Sha512, HELLO_SHA256, "x").is_err());
+        assert!(ExpectedDigest::from_hex(DigestAlgorithm::Sha256, &"zz".repeat(32), "x").is_err());
+        assert!(ExpectedDigest::from_hex(DigestAlgorithm::Sha256, &"é".repeat(32), "x").is_err());
+    }
+
+    #[test]
+    fn parses_base64_digests() {
+        let encoded =
+            "m3HSJL1i83hdltRq0+o9czGb+8KJDKra4t/3JRlnPKcjI8PZm6XBHXx6zG4UuMXaDEZjR1wuXDre9G9zvN7AQw==";
+        let digest =
+            ExpectedDigest::from_base64(DigestAlgorithm::Sha512, encoded, "catalog").unwrap();
+        assert_eq!(
+            digest.value,
+            DigestAlgorithm::Sha512
+                .compute_reader(&b"hello"[..])
+                .unwrap()
+        );
+
+        let error =
+            ExpectedDigest::from_base64(DigestAlgorithm::Sha256, encoded, "catalog").unwrap_err();
+        assert!(error.contains("expected 32 bytes, got 64"), "{}", error);
+        assert!(ExpectedDigest::from_base64(DigestAlgorithm::Sha512, "not base64!", "x").is_err());
+    }
+
+    #[test]
+    fn finds_digests_in_checksum_files() {
+        let sha256 = DigestAlgorithm::Sha256;
+        let other = "0".repeat(64);
+        let sums = format!(
+            "{}  netcoredbg-linux-x64.tar.gz\n\n{} *netcoredbg-osx-arm64.tar.gz\n",
+            other, HELLO_SHA256
+        );
+        let digest = ExpectedDigest::from_checksum_file(
+            sha256,
+            &sums,
+            "netcoredbg-osx-arm64.tar.gz",
+            "SUMS",
+        )
+        .unwrap();
+        assert_eq!(to_hex(&digest.value), HELLO_SHA256);
+        assert!(ExpectedDigest::from_checksum_file(
+            sha256,
+            &sums,
+            "netcoredbg-win-x64.zip",
+            "SUMS"
+        )
+        .is_err());
+
+        // A sidecar holds the digest alone
+        let sidecar = format!("{}\n", HELLO_SHA256);
+        let digest =
+            ExpectedDigest::from_checksum_file(sha256, &sidecar, "any.tar.gz", "any.sha256")
+                .unwrap();
+        assert_eq!(to_hex(&digest.value), HELLO_SHA256);
+    }
+
+    #[test]
+    fn verifies_files() {
+        let fs = MemoryFileSystem::new();
+        fs.put("hello.tar.gz", b"hello");
+        let digest =
+            ExpectedDigest::from_hex(DigestAlgorithm::Sha256, HELLO_SHA256, "hello.sha256")
+                .unwrap();
+        assert_eq!(
+            digest.clone().with_size(5).verify_file(&fs, "hello.tar.gz"),
+            Ok(())
+        );
+
+        let error = digest
+            .clone()
+            .with_size(6)
+            .verify_file(&fs, "hello.tar.gz")
+            .unwrap_err();
+        assert!(error.starts_with("size mismatch"), "{}", error);
+
+        fs.put("hello.tar.gz", b"HELLO");
+        let error = digest.verify_file(&fs, "hello.tar.gz").unwrap_err();
+        assert!(error.starts_with("checksum mismatch"), "{}", error);
+        assert!(digest.verify_file(&fs, "missing.tar.gz").is_err());
+    }
+}
@@ -1,37 +1,257 @@
+mod base64;
 mod binary_manager;
+mod checksum;
+mod dotnet;
+mod error;
+mod extension_settings;
+mod file_system;
+mod http;
+mod install_lock;
+mod install_manifest;
+mod launch_history;
 mod logger;
+mod maintenance;
+mod nuget;
+mod nuget_config;
 mod path_utils;
+mod platform;
+mod progress;
+mod release_source;
+mod tool_manifest;
+mod update_state;
+mod validation;
+mod version;
 mod version_config;
 
 // Language server identifiers
 const DEBUG_ADAPTER_NETCOREDBG: &str = "netcoredbg";
 const LANGUAGE_SERVER_NAME: &str = "csharp-language-server";
-
-use binary_manager::BinaryManager;
-use std::fs;
-use version_config::{csharp_language_server_config, netcoredbg_config,};
+/// Slash command showing, and on request cleaning up, the installed tools
+const TOOLS_COMMAND: &str = "csharp-tools";
+
+use binary_manager::{BinaryManager, InstallOptions};
+use dotnet::{DotnetRuntimes, RollForward, RuntimeConfig};
+use error::InstallError;
+use extension_settings::ExtensionSettings;
+use launch_history::LaunchHistory;
+use nuget_config::NugetConfig;
+use progress::{InstallProgress, InstallStatus, ToolProgress};
+use version::{Version, VersionReq};
+use version_config::{csharp_language_server_config, netcoredbg_config, VersionDirConfig};
 use zed_extension_api::{
-    self as zed,
-    serde_json::{Value},
-    settings::LspSettings,
-    DebugAdapterBinary, DebugTaskDefinition, LanguageServerId, Result,
-    StartDebuggingRequestArguments, StartDebuggingRequestArgumentsRequest, Worktree,
+    self as zed, serde_json::Value, settings::LspSettings, DebugAdapterBinary, DebugTaskDefinition,
+    LanguageServerId, Result, SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput,
+    SlashCommandOutputSection, StartDebuggingRequestArguments,
+    StartDebuggingRequestArgumentsRequest, Worktree,
 };
 
 struct CsharpExtension {
     binary_manager: BinaryManager,
     cached_debugger_path: Option<String>,
     cached_language_server_path: Option<String>,
-    platform_os: zed::Os,
+    /// Version requirements the cached paths were resolved for
+    cached_debugger_version: Option<VersionReq>,
+    cached_language_server_version: Option<VersionReq>,
+    /// Installed version most recently handed to Zed, confirmed once the server starts
+    launched_language_server_version: Option<String>,
+    _platform_os: zed::Os,
     _platform_arch: zed::Architecture,
 }
 
 impl CsharpExtension {
+    /// Path of the language server to launch: the resolved version, unless the installed
+    /// runtimes can't run it, or it keeps failing to start and a known-good version is
+    /// still installed to fall back to
     fn get_language_server_path(
         &mut self,
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<String> {
+        dotnet_path: &str,
+        roll_forward: Option<RollForward>,
+    ) -> Result<String, InstallError> {
+        // Knowing the installed runtimes lets version discovery skip packages that can't run
+        let runtimes = DotnetRuntimes::detect(dotnet_path).unwrap_or_else(|e| {
+            logger::Logger::warn(&format!(
+                "get_language_server_path: could not list .NET runtimes: {}",
+                e
+            ));
+            DotnetRuntimes::default()
+        });
+        let server_path =
+            self.resolve_language_server_path(language_server_id, worktree, &runtimes)?;
+
+        let config = csharp_language_server_config();
+        // User-defined paths aren't managed installs, so there is nothing to roll back to
+        let Some(version) = BinaryManager::installed_version(&config, &server_path) else {
+            self.launched_language_server_version = None;
+            return Ok(server_path);
+        };
+
+        let requirement = ExtensionSettings::for_worktree(worktree)
+            .and_then(|settings| settings.language_server_version())
+            .map_err(InstallError::Config)?;
+        let (version, server_path) = self.runnable_version(
+            &config,
+            version,
+            server_path,
+            &runtimes,
+            roll_forward,
+            requirement.as_ref(),
+        )?;
+
+        let mut history = LaunchHistory::load(self.binary_manager.fs());
+        let rollback = history
+            .rollback_target(&config.prefix, &version)
+            .filter(|known_good| {
+                requirement.as_ref().is_none_or(|requirement| {
+                    Version::parse(known_good)
+                        .is_ok_and(|known_good| requirement.matches(&known_good))
+                })
+            })
+            .and_then(|known_good| {
+                Some((
+                    known_good.to_string(),
+                    self.binary_manager
+                        .installed_binary_path(&config, known_good)?,
+                ))
+            });
+
+        let (version, server_path) = match rollback {
+            Some((known_good, known_good_path)) => {
+                logger::Logger::warn(&format!(
+                    "get_language_server_path: {} {} failed to start {} times, rolling back to {}",
+                    LANGUAGE_SERVER_NAME,
+                    version,
+                    launch_history::MAX_UNCONFIRMED_LAUNCHES,
+                    known_good
+                ));
+                (known_good, known_good_path)
+            }
+            None => (version, server_path),
+        };
+
+        history.record_launch(&config.prefix, &version);
+        if let Err(e) = history.save(self.binary_manager.fs()) {
+            logger::Logger::warn(&format!("get_language_server_path: {}", e));
+        }
+        self.launched_language_server_version = Some(version);
+        Ok(server_path)
+    }
+
+    /// `version` if the installed runtimes can start it, otherwise the newest other
+    /// installed version matching `requirement` that they can start
+    fn runnable_version(
+        &self,
+        config: &VersionDirConfig,
+        version: String,
+        server_path: String,
+        runtimes: &DotnetRuntimes,
+        roll_forward: Option<RollForward>,
+        requirement: Option<&VersionReq>,
+    ) -> Result<(String, String), InstallError> {
+        let missing = self.missing_runtimes(&server_path, runtimes, roll_forward);
+        if missing.is_empty() {
+            return Ok((version, server_path));
+        }
+
+        for (installed, raw_version) in self.binary_manager.installed_versions(config) {
+            if raw_version == version
+                || !requirement.is_none_or(|requirement| requirement.matches(&installed))
+            {
+                continue;
+            }
+            if let Some(path) = self
+                .binary_manager
+                .installed_binary_path(config, &raw_version)
+            {
+                if self
+                    .missing_runtimes(&path, runtimes, roll_forward)
+                    .is_empty()
+                {
+                    logger::Logger::warn(&format!(
+                        "get_language_server_path: {} {} needs {}, falling back to {}",
+                        LANGUAGE_SERVER_NAME,
+                        version,
+                        missing.join(", "),
+                        raw_version
+                    ));
+                    return Ok((raw_version, path));
+                }
+            }
+        }
+
+        let installed = runtimes
+            .versions_of(dotnet::NETCORE_APP)
+            .map(|version| version.to_string())
+            .collect::<Vec<_>>();
+        // Only suggest rolling forward when that would actually find a runtime
+        let roll_forward_hint = if roll_forward.is_none()
+            && self
+                .missing_runtimes(&server_path, runtimes, Some(RollForward::Major))
+                .is_empty()
+        {
+            format!(
+                "; set lsp.{}.settings.roll_forward to \"Major\" to run it on a newer runtime",
+                LANGUAGE_SERVER_NAME
+            )
+        } else {
+            String::new()
+        };
+        Err(InstallError::MissingRuntime(format!(
+            "{} {} needs {}, which no installed runtime satisfies (installed {}: {}){}",
+            LANGUAGE_SERVER_NAME,
+            version,
+            missing.join(", "),
+            dotnet::NETCORE_APP,
+            if installed.is_empty() {
+                "none".to_string()
+            } else {
+                installed.join(", ")
+            },
+            roll_forward_hint
+        )))
+    }
+
+    /// Frameworks the server at `server_path` needs that aren't installed, as
+    /// `<name> <version>`; empty when it can run or that can't be determined.
+    /// `roll_forward` overrides the policy in its runtimeconfig.
+    fn missing_runtimes(
+        &self,
+        server_path: &str,
+        runtimes: &DotnetRuntimes,
+        roll_forward: Option<RollForward>,
+    ) -> Vec<String> {
+        if runtimes.is_empty() {
+            return Vec::new();
+        }
+        let runtime_config_path = format!(
+            "{}.runtimeconfig.json",
+            server_path.strip_suffix(".dll").unwrap_or(server_path)
+        );
+        match RuntimeConfig::load(self.binary_manager.fs(), &runtime_config_path) {
+            Ok(mut runtime_config) => {
+                if let Some(roll_forward) = roll_forward {
+                    runtime_config.roll_forward = roll_forward;
+                }
+                runtimes
+                    .missing_frameworks(&runtime_config)
+                    .into_iter()
+                    .map(|framework| format!("{} {}", framework.name, framework.version))
+                    .collect()
+            }
+            Err(e) => {
+                logger::Logger::debug(&format!("get_language_server_path: {}", e));
+                Vec::new()
+            }
+        }
+    }
+
+    fn resolve_language_server_path(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+        runtimes: &DotnetRuntimes,
+    ) -> Result<String, InstallError> {
         logger::Logger::debug(&format!(
             "get_language_server_path: starting {} path resolution",
             LANGUAGE_SERVER_NAME
@@ -51,9 +271,20 @@ impl CsharpExtension {
             return Ok(absolute_path);
         }
 
-        // Check for cached path
+        let settings = ExtensionSettings::for_worktree(worktree).map_err(InstallError::Config)?;
+        let version = settings
+            .language_server_version()
+            .map_err(InstallError::Config)?;
+
+        // Check for cached path, unless the pinned version changed since
+        if self.cached_language_server_version != version {
+            self.cached_language_server_path = None;
+        }
         if let Some(path) = &self.cached_language_server_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+            if self
+                .binary_manager
+                .is_reusable(&csharp_language_server_config(), path)
+            {
                 logger::Logger::debug(&format!(
                     "get_language_server_path: using cached path: {}",
                     path
@@ -64,55 +295,45 @@ impl CsharpExtension {
 
         logger::Logger::debug("get_language_server_path: resolving version directory");
 
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-        );
-
-        let config = csharp_language_server_config();
-        let version_dir = self
-            .binary_manager
-            .get_version_dir(&config, Some(language_server_id))?;
+        language_server_id.report(&InstallStatus::CheckingForUpdate);
+
+        let config = self.language_server_config(&settings, Some(worktree))?;
+        let options = InstallOptions {
+            version: version.clone(),
+            runtimes: runtimes.clone(),
+            offline: settings.offline,
+            local_archive: settings.language_server_archive(),
+            update_check_interval: settings
+                .update_check_interval()
+                .map_err(InstallError::Config)?,
+            retain_versions: settings.retain_versions(),
+            known_good: LaunchHistory::load(self.binary_manager.fs())
+                .known_good(&config.prefix)
+                .map(str::to_string),
+        };
+        let version_dir =
+            self.binary_manager
+                .get_version_dir(&config, &options, language_server_id)?;
 
-        let server_path = (config.get_binary_path)(&version_dir);
+        let server_path = config.binary_path(self.binary_manager.fs(), &version_dir);
         logger::Logger::debug(&format!(
             "get_language_server_path: resolved path {}",
             server_path
         ));
 
-        if !fs::metadata(&server_path).map_or(false, |stat| stat.is_file()) {
+        if !self.binary_manager.fs().is_file(&server_path) {
             logger::Logger::debug("get_language_server_path: failed to find binary");
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::Failed(format!(
-                    "{} not found at: {}",
-                    LANGUAGE_SERVER_NAME, server_path
-                )),
-            );
-            return Err(format!(
+            return Err(InstallError::Other(format!(
                 "{} binary not found at: {}",
                 LANGUAGE_SERVER_NAME, server_path
-            ));
-        }
-
-        // Make executable on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Ok(metadata) = fs::metadata(&server_path) {
-                let mut perms = metadata.permissions();
-                perms.set_mode(0o755);
-                fs::set_permissions(&server_path, perms).ok();
-            }
+            )));
         }
 
         // Cache the path before returning
         self.cached_language_server_path = Some(server_path.clone());
+        self.cached_language_server_version = version;
 
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::None,
-        );
+        language_server_id.report(&InstallStatus::Ready);
         logger::Logger::debug(&format!(
             "get_language_server_path: found and cached at {}",
             server_path
@@ -120,7 +341,119 @@ impl CsharpExtension {
         Ok(server_path)
     }
 
-    fn get_debugger_path(&mut self, user_provided_path: Option<String>) -> Result<String, String> {
+    /// Where csharp-language-server comes from: an explicit `source` setting wins over the
+    /// package sources in the worktree's nuget.config
+    fn language_server_config(
+        &self,
+        settings: &ExtensionSettings,
+        worktree: Option<&Worktree>,
+    ) -> Result<VersionDirConfig, InstallError> {
+        let env = worktree.map(Worktree::shell_env).unwrap_or_default();
+        let mut config = settings
+            .language_server_config()
+            .map_err(InstallError::Config)?;
+        if let (None, Some(worktree)) = (&settings.source, worktree) {
+            if let Some(nuget_config) = NugetConfig::discover(worktree, &env)? {
+                config = nuget_config.apply_to(config)?;
+            }
+        }
+        settings
+            .language_server_source(config, &env)
+            .map_err(InstallError::Config)
+    }
+
+    /// Disk usage of the installed tools, after cleaning them up the way installs do when
+    /// `collect` is set. What the `csharp-tools` slash command shows.
+    fn tools_report(
+        &self,
+        worktree: Option<&Worktree>,
+        collect: bool,
+    ) -> Result<String, InstallError> {
+        let settings = match worktree {
+            Some(worktree) => {
+                ExtensionSettings::for_worktree(worktree).map_err(InstallError::Config)?
+            }
+            None => ExtensionSettings::default(),
+        };
+        let env = worktree.map(Worktree::shell_env).unwrap_or_default();
+        let language_server = self.language_server_config(&settings, worktree)?;
+        let debugger = settings
+            .debugger_config()
+            .and_then(|config| settings.debugger_source(config, &env))
+            .map_err(InstallError::Config)?;
+        let tools = [
+            (
+                InstallOptions {
+                    version: settings
+                        .language_server_version()
+                        .map_err(InstallError::Config)?,
+                    local_archive: settings.language_server_archive(),
+                    retain_versions: settings.retain_versions(),
+                    known_good: LaunchHistory::load(self.binary_manager.fs())
+                        .known_good(&language_server.prefix)
+                        .map(str::to_string),
+                    ..Default::default()
+                },
+                language_server,
+                self.cached_language_server_path.as_deref(),
+            ),
+            (
+                InstallOptions {
+                    version: settings.debugger_version().map_err(InstallError::Config)?,
+                    local_archive: settings.debugger_archive(),
+                    retain_versions: settings.retain_versions(),
+                    ..Default::default()
+                },
+                debugger,
+                self.cached_debugger_path.as_deref(),
+            ),
+        ];
+
+        let mut report = String::new();
+        for (options, config, cached_path) in &tools {
+            if collect {
+                // Keep the version this window runs, and the one it would start next
+                let newest = self
+                    .binary_manager
+                    .installed_versions(config)
+                    .into_iter()
+                    .find(|(version, _)| {
+                        options
+                            .version
+                            .as_ref()
+                            .is_none_or(|requirement| requirement.matches(version))
+                    })
+                    .map(|(_, raw)| raw);
+                let in_use: Vec<String> = cached_path
+                    .and_then(|path| BinaryManager::installed_version(config, path))
+                    .into_iter()
+                    .chain(newest)
+                    .map(|version| format!("{}-{}", config.prefix, version))
+                    .collect();
+                let in_use: Vec<&str> = in_use.iter().map(String::as_str).collect();
+                let gc = self.binary_manager.clean_up(config, options, &in_use)?;
+                report.push_str(&format!(
+                    "Removed {} entries of {}, freeing {}\n",
+                    gc.removed.len(),
+                    config.prefix,
+                    maintenance::format_size(gc.freed)
+                ));
+            }
+            let entries = self.binary_manager.inventory(config)?;
+            report.push_str(&maintenance::describe_usage(
+                &config.prefix,
+                &entries,
+                maintenance::now(),
+            ));
+        }
+        Ok(report)
+    }
+
+    fn get_debugger_path(
+        &mut self,
+        user_provided_path: Option<String>,
+        worktree: &Worktree,
+    ) -> Result<String, InstallError> {
         logger::Logger::debug("get_debugger_path: starting debugger path resolution");
 
         // check for user-defined path first
@@ -132,9 +465,15 @@ impl CsharpExtension {
             return Ok(user_path);
         }
 
-        // check for cached debugger path
+        let settings = ExtensionSettings::for_worktree(worktree).map_err(InstallError::Config)?;
+        let version = settings.debugger_version().map_err(InstallError::Config)?;
+
+        // check for cached debugger path, unless the pinned version changed since
+        if self.cached_debugger_version != version {
+            self.cached_debugger_path = None;
+        }
         if let Some(path) = &self.cached_debugger_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+            if self.binary_manager.is_reusable(&netcoredbg_config(), path) {
                 logger::Logger::debug(&format!(
                     "get_debugger_path: using cached debugger path: {}",
                     path
@@ -144,33 +483,44 @@ impl CsharpExtension {
         }
 
         logger::Logger::debug("get_debugger_path: getting version directory");
-        let config = netcoredbg_config();
+        let progress = ToolProgress {
+            tool: DEBUG_ADAPTER_NETCOREDBG,
+        };
+        progress.report(&InstallStatus::CheckingForUpdate);
+        let config = settings
+            .debugger_config()
+            .and_then(|config| settings.debugger_source(config, &worktree.shell_env()))
+            .map_err(InstallError::Config)?;
+        let options = InstallOptions {
+            version: version.clone(),
+            offline: settings.offline,
+            local_archive: settings.debugger_archive(),
+            update_check_interval: settings
+                .update_check_interval()
+                .map_err(InstallError::Config)?,
+            retain_versions: settings.retain_versions(),
+            ..Default::default()
+        };
         let version_dir = self
             .binary_manager
-            .get_version_dir(&config, None)?;
+            .get_version_dir(&config, &options, &progress)?;
 
-        let debugger_path = (config.get_binary_path)(&version_dir);
+        let debugger_path = config.binary_path(self.binary_manager.fs(), &version_dir);
 
-        if !fs::metadata(&debugger_path).map_or(false, |stat| stat.is_file()) {
-            return Err(format!(
+        if !self.binary_manager.fs().is_file(&debugger_path) {
+            return Err(InstallError::Other(format!(
                 "csharp debug server not found at: {}",
                 debugger_path
-            ));
+            )));
         }
 
-        // Make executable on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Ok(metadata) = fs::metadata(&debugger_path) {
-                let mut perms = metadata.permissions();
-                perms.set_mode(0o755);
-                fs::set_permissions(&debugger_path, perms).ok();
-            }
-        }
+        // Installs are marked executable when extracted, but older ones never were
+        self.binary_manager.make_executable(&debugger_path)?;
+        progress.report(&InstallStatus::Ready);
 
         // version_dir is already absolute, so debugger_path is absolute too
         self.cached_debugger_path = Some(debugger_path.clone());
+        self.cached_debugger_version = version;
         Ok(debugger_path)
     }
 }
@@ -182,7 +532,10 @@ impl zed::Extension for CsharpExtension {
             binary_manager: BinaryManager::new(),
             cached_debugger_path: None,
             cached_language_server_path: None,
-            platform_os: platform_os,
+            cached_debugger_version: None,
+            cached_language_server_version: None,
+            launched_language_server_version: None,
+            _platform_os: platform_os,
             _platform_arch: platform_arch,
         }
     }
@@ -211,10 +564,10 @@ impl zed::Extension for CsharpExtension {
         let configuration = config.config.to_string();
 
         let debugger_path = self
-            .get_debugger_path(user_provided_debug_adapter_path)
+            .get_debugger_path(user_provided_debug_adapter_path, worktree)
             .map_err(|e| {
                 logger::Logger::error(&format!("get_dap_binary: failed to locate debugger: {}", e));
-                format!("Failed to locate C# debugger: {}", e)
+                format!("Failed to locate C# debugger: {}", e.user_message())
             })?;
 
         logger::Logger::debug(&format!(
@@ -276,17 +629,32 @@ impl zed::Extension for CsharpExtension {
             server_id_str
         ));
 
-        let dotnet_path = worktree.which("dotnet").ok_or_else(|| {
-            "dotnet runtime not found. Please ensure .NET is installed and in your PATH.".to_string()
+        let roll_forward = ExtensionSettings::for_worktree(worktree)
+            .and_then(|settings| settings.roll_forward())
+            .map_err(InstallError::Config);
+        let server_path = roll_forward.and_then(|roll_forward| {
+            let dotnet_path = worktree.which("dotnet").ok_or_else(|| {
+                InstallError::MissingRuntime("dotnet runtime not found".to_string())
+            })?;
+            logger::Logger::debug(&format!(
+                "language_server_command: using dotnet at: {}",
+                dotnet_path
+            ));
+            let server_path = self.get_language_server_path(
+                language_server_id,
+                worktree,
+                &dotnet_path,
+                roll_forward,
+            )?;
+            Ok((dotnet_path, server_path, roll_forward))
+        });
+        let (dotnet_path, server_path, roll_forward) = server_path.map_err(|e| {
+            logger::Logger::error(&format!("language_server_command: {}", e));
+            let message = e.user_message();
+            language_server_id.report(&InstallStatus::Failed(message.clone()));
+            message
         })?;
 
-        logger::Logger::debug(&format!(
-            "language_server_command: using dotnet at: {}",
-            dotnet_path
-        ));
-
-        let server_path = self.get_language_server_path(language_server_id, worktree)?;
-
         logger::Logger::debug(&format!(
             "language_server_command: using {} at: {}",
             LANGUAGE_SERVER_NAME, server_path
@@ -294,10 +662,83 @@ impl zed::Extension for CsharpExtension {
 
         Ok(zed::Command {
             command: dotnet_path,
-            args: vec![server_path],
+            args: dotnet::host_args(roll_forward, &server_path),
             env: Default::default(),
         })
     }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        _worktree: &Worktree,
+    ) -> Result<Option<Value>> {
+        // Zed asks for the workspace configuration once the server has initialized,
+        // which is the first sign the launched version actually starts
+        if let Some(version) = &self.launched_language_server_version {
+            let mut history = LaunchHistory::load(self.binary_manager.fs());
+            if history.record_success(&csharp_language_server_config().prefix, version) {
+                logger::Logger::debug(&format!(
+                    "language_server_workspace_configuration: {} {} started successfully",
+                    LANGUAGE_SERVER_NAME, version
+                ));
+                if let Err(e) = history.save(self.binary_manager.fs()) {
+                    logger::Logger::warn(&format!(
+                        "language_server_workspace_configuration: {}",
+                        e
+                    ));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn complete_slash_command_argument(
+        &self,
+        command: SlashCommand,
+        _args: Vec<String>,
+    ) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+        match command.name.as_str() {
+            TOOLS_COMMAND => Ok(vec![SlashCommandArgumentCompletion {
+                label: "gc: remove unused versions and leftovers".to_string(),
+                new_text: "gc".to_string(),
+                run_command: true,
+            }]),
+            name => Err(format!("unknown slash command: {}", name)),
+        }
+    }
+
+    fn run_slash_command(
+        &self,
+        command: SlashCommand,
+        args: Vec<String>,
+        worktree: Option<&Worktree>,
+    ) -> Result<SlashCommandOutput, String> {
+        if command.name != TOOLS_COMMAND {
+            return Err(format!("unknown slash command: {}", command.name));
+        }
+        let collect = match args.first().map(String::as_str) {
+            None => false,
+            Some("gc") => true,
+            Some(arg) => {
+                return Err(format!(
+                    "unknown argument {} for /{}, expected gc or nothing",
+                    arg, TOOLS_COMMAND
+                ))
+            }
+        };
+
+        let text = self.tools_report(worktree, collect).map_err(|e| {
+            logger::Logger::error(&format!("run_slash_command: {}", e));
+            e.user_message()
+        })?;
+        Ok(SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: "C# tools".to_string(),
+            }],
+            text,
+        })
+    }
 }
 
 zed::register_extension!(CsharpExtension);
@@ -0,0 +1,451 @@
+use zed_extension_api::{self as zed, Result};
+
+use crate::file_system::FileSystem;
+use crate::logger;
+use crate::version::Version;
+
+/// Name of the shared framework that console apps like csharp-ls run on
+pub const NETCORE_APP: &str = "Microsoft.NETCore.App";
+
+/// A .NET target framework moniker such as `net9.0` or `netcoreapp3.1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TargetFramework {
+    pub major: u64,
+    pub minor: u64,
+}
+
+impl TargetFramework {
+    /// Parse short (`net9.0`, `netcoreapp3.1`) and long (`.NETCoreApp,Version=v8.0`,
+    /// `.NETCoreApp8.0`) forms. Platform suffixes (`net8.0-windows`) are ignored.
+    /// Returns `None` for .NET Framework and .NET Standard monikers.
+    pub fn parse(moniker: &str) -> Option<Self> {
+        let lower = moniker.trim().to_ascii_lowercase();
+        let version = if let Some(rest) = lower.strip_prefix(".netcoreapp") {
+            rest.trim_start_matches(",version=").trim_start_matches('v')
+        } else if let Some(rest) = lower.strip_prefix("netcoreapp") {
+            rest
+        } else if let Some(rest) = lower.strip_prefix("net") {
+            // `net48` and friends are .NET Framework; .NET 5+ always has a dot
+            if !rest.contains('.') {
+                return None;
+            }
+            rest
+        } else {
+            return None;
+        };
+
+        let version = version.split('-').next().unwrap_or_default();
+        let (major, minor) = version.split_once('.')?;
+        Some(Self {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+}
+
+impl std::fmt::Display for TargetFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.major >= 5 {
+            write!(f, "net{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "netcoreapp{}.{}", self.major, self.minor)
+        }
+    }
+}
+
+/// How the host may substitute a newer runtime for the requested one
+/// (`rollForward` in runtimeconfig.json)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RollForward {
+    /// Exactly the requested version
+    Disable,
+    /// Same major and minor version
+    LatestPatch,
+    /// Same major version; the default
+    #[default]
+    Minor,
+    LatestMinor,
+    /// Any newer version
+    Major,
+    LatestMajor,
+}
+
+impl RollForward {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "disable" => Some(Self::Disable),
+            "latestpatch" => Some(Self::LatestPatch),
+            "minor" => Some(Self::Minor),
+            "latestminor" => Some(Self::LatestMinor),
+            "major" => Some(Self::Major),
+            "latestmajor" => Some(Self::LatestMajor),
+            _ => None,
+        }
+    }
+
+    /// The policy as `--roll-forward` and runtimeconfig.json spell it
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Disable => "Disable",
+            Self::LatestPatch => "LatestPatch",
+            Self::Minor => "Minor",
+            Self::LatestMinor => "LatestMinor",
+            Self::Major => "Major",
+            Self::LatestMajor => "LatestMajor",
+        }
+    }
+
+    /// Whether an app asking for `requested` can run on `installed` under this policy
+    pub fn accepts(self, requested: &Version, installed: &Version) -> bool {
+        let same_major = installed.major == requested.major;
+        let same_minor = same_major && installed.minor == requested.minor;
+        match self {
+            Self::Disable => installed == requested,
+            Self::LatestPatch => same_minor && installed >= requested,
+            Self::Minor | Self::LatestMinor => same_major && installed >= requested,
+            Self::Major | Self::LatestMajor => installed >= requested,
+        }
+    }
+}
+
+/// A shared framework an app needs, from its runtimeconfig.json
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameworkReference {
+    pub name: String,
+    pub version: Version,
+}
+
+/// The `runtimeOptions` of an app's `<name>.runtimeconfig.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    pub frameworks: Vec<FrameworkReference>,
+    pub roll_forward: RollForward,
+}
+
+impl RuntimeConfig {
+    pub fn load(fs: &dyn FileSystem, path: &str) -> Result<Self> {
+        let json = fs
+            .read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path, e))?;
+        Self::parse(&json).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(json: &str) -> Result<Self> {
+        let config: zed::serde_json::Value =
+            zed::serde_json::from_str(json).map_err(|e| format!("invalid runtimeconfig: {}", e))?;
+        let options = config
+            .get("runtimeOptions")
+            .ok_or_else(|| "runtimeconfig has no runtimeOptions".to_string())?;
+
+        // Either a single `framework` or, for apps on several shared frameworks, `frameworks`
+        let frameworks = options
+            .get("frameworks")
+            .and_then(|frameworks| frameworks.as_array())
+            .map(|frameworks| frameworks.iter().collect::<Vec<_>>())
+            .or_else(|| options.get("framework").map(|framework| vec![framework]))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|framework| {
+                let name = framework.get("name").and_then(|v| v.as_str());
+                let version = framework.get("version").and_then(|v| v.as_str());
+                match (name, version) {
+                    (Some(name), Some(version)) => Ok(FrameworkReference {
+                        name: name.to_string(),
+                        version: Version::parse(version)?,
+                    }),
+                    _ => Err("runtimeconfig framework without name or version".to_string()),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        let roll_forward = match options.get("rollForward").and_then(|v| v.as_str()) {
+            Some(value) => RollForward::parse(value)
+                .ok_or_else(|| format!("unknown rollForward policy: {}", value))?,
+            None => RollForward::default(),
+        };
+
+        Ok(Self {
+            frameworks,
+            roll_forward,
+        })
+    }
+}
+
+/// Arguments for `dotnet` to run the app at `app_path`. The policy is passed as a host
+/// option rather than DOTNET_ROLL_FORWARD, so processes the app spawns keep their own.
+pub fn host_args(roll_forward: Option<RollForward>, app_path: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(roll_forward) = roll_forward {
+        args.push("--roll-forward".to_string());
+        args.push(roll_forward.name().to_string());
+    }
+    args.push(app_path.to_string());
+    args
+}
+
+/// Framework folders in `parent` that contain `file`, e.g. the `<tfm>` folders of a
+/// dotnet tool package's `tools/<tfm>/any/<assembly>`, as (parsed, folder name) pairs
+pub fn framework_folders(
+    fs: &dyn FileSystem,
+    parent: &str,
+    file: &str,
+) -> Vec<(TargetFramework, String)> {
+    let Ok(entries) = fs.read_dir(parent) else {
+        return Vec::new();
+    };
+
+    let mut frameworks: Vec<(TargetFramework, String)> = entries
+        .into_iter()
+        .filter(|folder| fs.is_file(&format!("{}/{}/{}", parent, folder, file)))
+        .filter_map(|folder| Some((TargetFramework::parse(&folder)?, folder)))
+        .collect();
+    frameworks.sort();
+    frameworks
+}
+
+/// A shared runtime reported by `dotnet --list-runtimes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledRuntime {
+    pub name: String,
+    pub version: Version,
+}
+
+/// The set of .NET runtimes installed on the machine; empty when unknown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DotnetRuntimes {
+    pub runtimes: Vec<InstalledRuntime>,
+}
+
+impl DotnetRuntimes {
+    /// Run `dotnet --list-runtimes` through the process API
+    pub fn detect(dotnet_path: &str) -> Result<Self> {
+        let output = zed::process::Command::new(dotnet_path)
+            .arg("--list-runtimes")
+            .output()
+            .map_err(|e| format!("failed to run {} --list-runtimes: {}", dotnet_path, e))?;
+
+        if output.status != Some(0) {
+            return Err(format!(
+                "{} --list-runtimes exited with {:?}: {}",
+                dotnet_path,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let runtimes = Self::parse(&String::from_utf8_lossy(&output.stdout));
+        logger::Logger::debug(&format!(
+            "DotnetRuntimes::detect: found {} runtimes",
+            runtimes.runtimes.len()
+        ));
+        Ok(runtimes)
+    }
+
+    /// Parse lines like `Microsoft.NETCore.App 8.0.11 [/usr/share/dotnet/shared/Microsoft.NETCore.App]`
+    pub fn parse(output: &str) -> Self {
+        let runtimes = output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let name = parts.next()?;
+                let version = Version::parse(parts.next()?).ok()?;
+                Some(InstalledRuntime {
+                    name: name.to_string(),
+                    version,
+                })
+            })
+            .collect();
+        Self { runtimes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runtimes.is_empty()
+    }
+
+    /// Installed versions of the given shared framework
+    pub fn versions_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Version> + 'a {
+        self.runtimes
+            .iter()
+            .filter(move |runtime| runtime.name == name)
+            .map(|runtime| &runtime.version)
+    }
+
+    /// Whether an app targeting `tfm` can start here under the default roll-forward
+    /// policy (`Minor`): same major version, equal or higher minor
+    pub fn can_run(&self, tfm: &TargetFramework) -> bool {
+        self.versions_of(NETCORE_APP)
+            .any(|version| version.major == tfm.major && version.minor >= tfm.minor)
+    }
+
+    /// Frameworks of `config` that no installed runtime satisfies under its roll-forward
+    /// policy; empty when the app can start
+    pub fn missing_frameworks<'a>(&self, config: &'a RuntimeConfig) -> Vec<&'a FrameworkReference> {
+        config
+            .frameworks
+            .iter()
+            .filter(|framework| {
+                !self
+                    .versions_of(&framework.name)
+                    .any(|installed| config.roll_forward.accepts(&framework.version, installed))
+            })
+            .collect()
+    }
+
+    /// The highest of `frameworks` these runtimes can run; the highest overall when
+    /// the runtimes are unknown or can run none of them
+    pub fn best_framework<'a, T>(
+        &self,
+        frameworks: &'a [(TargetFramework, T)],
+    ) -> Option<&'a (TargetFramework, T)> {
+        frameworks
+            .iter()
+            .filter(|(tfm, _)| self.can_run(tfm))
+            .max_by_key(|(tfm, _)| *tfm)
+            .or_else(|| frameworks.iter().max_by_key(|(tfm, _)| *tfm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension_settings::ExtensionSettings;
+
+    #[test]
+    fn parses_target_framework_monikers() {
+        let tfm = |major, minor| Some(TargetFramework { major, minor });
+        assert_eq!(TargetFramework::parse("net9.0"), tfm(9, 0));
+        assert_eq!(TargetFramework::parse("net10.0"), tfm(10, 0));
+        assert_eq!(TargetFramework::parse("net8.0-windows"), tfm(8, 0));
+        assert_eq!(TargetFramework::parse("netcoreapp3.1"), tfm(3, 1));
+        assert_eq!(
+            TargetFramework::parse(".NETCoreApp,Version=v8.0"),
+            tfm(8, 0)
+        );
+        assert_eq!(TargetFramework::parse("net48"), None);
+        assert_eq!(TargetFramework::parse("netstandard2.0"), None);
+        assert!(TargetFramework::parse("net10.0") > TargetFramework::parse("net9.0"));
+    }
+
+    #[test]
+    fn checks_runtimes_under_default_roll_forward() {
+        let runtimes = DotnetRuntimes::parse(
+            "Microsoft.AspNetCore.App 9.0.1 [/usr/share/dotnet/shared/Microsoft.AspNetCore.App]\n\
+             Microsoft.NETCore.App 8.0.11 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n\
+             Microsoft.NETCore.App 9.0.1 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n",
+        );
+
+        assert_eq!(runtimes.versions_of(NETCORE_APP).count(), 2);
+        assert!(runtimes.can_run(&TargetFramework { major: 9, minor: 0 }));
+        assert!(runtimes.can_run(&TargetFramework { major: 8, minor: 0 }));
+        assert!(!runtimes.can_run(&TargetFramework {
+            major: 10,
+            minor: 0
+        }));
+        assert!(!runtimes.can_run(&TargetFramework { major: 7, minor: 0 }));
+    }
+
+    #[test]
+    fn checks_runtimeconfig_frameworks() {
+        let runtimes = DotnetRuntimes::parse(
+            "Microsoft.NETCore.App 8.0.11 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n",
+        );
+        let config = RuntimeConfig::parse(
+            r#"{
+              "runtimeOptions": {
+                "tfm": "net9.0",
+                "framework": { "name": "Microsoft.NETCore.App", "version": "9.0.0" }
+              }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.roll_forward, RollForward::Minor);
+        assert_eq!(
+            runtimes.missing_frameworks(&config),
+            vec![&config.frameworks[0]]
+        );
+
+        let config = RuntimeConfig::parse(
+            r#"{
+              "runtimeOptions": {
+                "rollForward": "LatestMajor",
+                "frameworks": [{ "name": "Microsoft.NETCore.App", "version": "6.0.0" }]
+              }
+            }"#,
+        )
+        .unwrap();
+        assert!(runtimes.missing_frameworks(&config).is_empty());
+
+        let policy = RollForward::parse("latestmajor").unwrap();
+        assert_eq!(RollForward::parse(policy.name()), Some(policy));
+        assert!(RollForward::Major
+            .accepts(&config.frameworks[0].version, &runtimes.runtimes[0].version));
+        assert!(!RollForward::LatestPatch
+            .accepts(&config.frameworks[0].version, &runtimes.runtimes[0].version));
+    }
+
+    #[test]
+    fn passes_the_configured_roll_forward_to_the_host() {
+        let settings = ExtensionSettings {
+            roll_forward: Some("latestMajor".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            host_args(
+                settings.roll_forward().unwrap(),
+                "/ls/CSharpLanguageServer.dll"
+            ),
+            vec![
+                "--roll-forward",
+                "LatestMajor",
+                "/ls/CSharpLanguageServer.dll"
+            ]
+        );
+        assert_eq!(
+            host_args(
+                ExtensionSettings::default().roll_forward().unwrap(),
+                "/ls/CSharpLanguageServer.dll"
+            ),
+            vec!["/ls/CSharpLanguageServer.dll"]
+        );
+
+        let settings = ExtensionSettings {
+            roll_forward: Some("sideways".to_string()),
+            ..Default::default()
+        };
+        let error = settings.roll_forward().unwrap_err();
+        assert!(error.contains("unknown policy sideways"), "{}", error);
+    }
+
+    #[test]
+    fn picks_the_highest_runnable_framework() {
+        let runtimes = DotnetRuntimes::parse(
+            "Microsoft.NETCore.App 8.0.11 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n\
+             Microsoft.NETCore.App 9.0.1 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n",
+        );
+        let frameworks = [
+            (TargetFramework { major: 8, minor: 0 }, "net8.0"),
+            (TargetFramework { major: 9, minor: 0 }, "net9.0"),
+            (
+                TargetFramework {
+                    major: 10,
+                    minor: 0,
+                },
+                "net10.0",
+            ),
+        ];
+
+        assert_eq!(
+            runtimes
+                .best_framework(&frameworks)
+                .map(|(_, folder)| *folder),
+            Some("net9.0")
+        );
+        assert_eq!(
+            DotnetRuntimes::default()
+                .best_framework(&frameworks)
+                .map(|(_, folder)| *folder),
+            Some("net10.0")
+        );
+        assert_eq!(runtimes.best_framework::<&str>(&[]), None);
+    }
+}
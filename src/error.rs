@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+/// Longest wait between download attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Why locating, downloading or installing a tool failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallError {
+    /// The request got no usable response (DNS, TLS, reset connection, empty body)
+    Network {
+        url: String,
+        message: String,
+    },
+    /// The server answered with an error status
+    HttpStatus {
+        url: String,
+        status: u16,
+    },
+    /// The server sent something other than an archive, typically an error page
+    UnexpectedContent {
+        url: String,
+        message: String,
+    },
+    /// The archive is truncated or can't be decoded
+    ArchiveCorrupt(String),
+    /// The archive doesn't match its published checksum
+    ChecksumMismatch(String),
+    /// No build of the tool is published for this OS and architecture
+    UnsupportedPlatform(String),
+    /// A .NET runtime the tool needs isn't installed
+    MissingRuntime(String),
+    /// The work directory, or a file in it, can't be written
+    Permission {
+        path: String,
+        message: String,
+    },
+    /// No version satisfies the configured version or range
+    VersionNotFound(String),
+    /// The tool isn't installed and `offline` forbids downloading it
+    Offline(String),
+    /// Another instance kept installing the same version for longer than we wait
+    Locked {
+        path: String,
+        waited: Duration,
+    },
+    /// The source publishes no checksum for an asset the tool's manifest requires one for
+    Unverified(String),
+    /// A setting is invalid
+    Config(String),
+    Other(String),
+}
+
+impl InstallError {
+    /// Classify an error reported by the HTTP client. The extension API only reports
+    /// failures as text, so the status code is read out of the message when present.
+    pub fn from_fetch(url: &str, message: &str) -> Self {
+        match http_status(message) {
+            Some(status) => InstallError::HttpStatus {
+                url: url.to_string(),
+                status,
+            },
+            None => InstallError::Network {
+                url: url.to_string(),
+                message: message.to_string(),
+            },
+        }
+    }
+
+    /// Classify a filesystem error on `path`
+    pub fn io(path: &str, error: &std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => InstallError::Permission {
+                path: path.to_string(),
+                message: error.to_string(),
+            },
+            _ => InstallError::Other(format!("{}: {}", path, error)),
+        }
+    }
+
+    /// Classify an error while reading `what` out of an archive: anything but a permission
+    /// problem means the archive data is bad
+    pub fn extract(what: &str, error: &std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => InstallError::io(what, error),
+            _ => InstallError::ArchiveCorrupt(format!("failed to extract {}: {}", what, error)),
+        }
+    }
+
+    /// Whether trying the same download again can succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            // A digest mismatch is no download glitch: the same bytes would be served again
+            InstallError::Network { .. } | InstallError::ArchiveCorrupt(_) => true,
+            InstallError::HttpStatus { status, .. } => {
+                matches!(status, 408 | 429) || *status >= 500
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the source failed to answer or to vouch for the asset, so another source
+    /// may still have the tool
+    pub fn is_source_failure(&self) -> bool {
+        matches!(
+            self,
+            InstallError::Network { .. }
+                | InstallError::HttpStatus { .. }
+                | InstallError::UnexpectedContent { .. }
+                | InstallError::Unverified(_)
+        )
+    }
+
+    /// How long to wait before retry number `attempt` (1-based); zero when not retryable
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = match self {
+            _ if !self.is_retryable() => return Duration::ZERO,
+            // Rate limits need longer than a flaky connection to clear
+            InstallError::HttpStatus { status: 429, .. } => Duration::from_secs(10),
+            _ => Duration::from_secs(1),
+        };
+        base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_BACKOFF)
+    }
+
+    /// What the user can do about it, if anything
+    pub fn remediation(&self) -> Option<&'static str> {
+        match self {
+            InstallError::Network { .. } => Some(
+                "Check your network connection and proxy, or set `offline` and side-load the archive with `archive`.",
+            ),
+            InstallError::HttpStatus { status: 401 | 403, .. } => {
+                Some("Check the credentials configured for this source (`token_env` or `token`).")
+            }
+            InstallError::HttpStatus { status: 404, .. } => {
+                Some("Check the `version` and `source` settings.")
+            }
+            InstallError::HttpStatus { status: 429, .. } => {
+                Some("The server is rate limiting requests; try again later.")
+            }
+            InstallError::HttpStatus { .. } => Some("The server failed; try again later."),
+            InstallError::UnexpectedContent { .. } => {
+                Some("Check the `source` URL and its credentials.")
+            }
+            InstallError::ArchiveCorrupt(_) => Some(
+                "Restart the language server to download it again, or pin another `version`.",
+            ),
+            InstallError::ChecksumMismatch(_) => Some(
+                "Restart the language server to download it again; if this persists, check any proxy or mirror in between.",
+            ),
+            InstallError::UnsupportedPlatform(_) => {
+                Some("Install the tool yourself and point `binary.path` at it.")
+            }
+            InstallError::MissingRuntime(_) => Some(
+                "Install .NET from https://dotnet.microsoft.com/download and make sure `dotnet` is on your PATH.",
+            ),
+            InstallError::Permission { .. } => {
+                Some("Make sure the extension's work directory is writable.")
+            }
+            InstallError::VersionNotFound(_) => {
+                Some("Check the `version` setting against the versions the source publishes.")
+            }
+            InstallError::Offline(_) => {
+                Some("Set `offline` to false, or side-load the archive with `archive`.")
+            }
+            InstallError::Locked { .. } => Some(
+                "Another Zed window is still installing it; restart the language server once it finishes.",
+            ),
+            InstallError::Unverified(_) => Some(
+                "Try again later, or configure a `source` or side-load the archive with `archive`.",
+            ),
+            InstallError::Config(_) => Some("Fix the setting in your Zed settings."),
+            InstallError::Other(_) => None,
+        }
+    }
+
+    /// The error and its remediation, as shown in Zed
+    pub fn user_message(&self) -> String {
+        match self.remediation() {
+            Some(remediation) => format!("{}. {}", self, remediation),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallError::Network { url, message } => {
+                write!(f, "failed to fetch {}: {}", url, message)
+            }
+            InstallError::HttpStatus { url, status } => {
+                write!(f, "{} responded with HTTP {}", url, status)
+            }
+            InstallError::UnexpectedContent { url, message } => {
+                write!(f, "unexpected response from {}: {}", url, message)
+            }
+            InstallError::ArchiveCorrupt(message) => write!(f, "corrupt archive: {}", message),
+            InstallError::ChecksumMismatch(message) => {
+                write!(f, "download failed verification: {}", message)
+            }
+            InstallError::Permission { path, message } => {
+                write!(f, "permission denied on {}: {}", path, message)
+            }
+            InstallError::Locked { path, waited } => write!(
+                f,
+                "gave up after {}s waiting for install lock {}",
+                waited.as_secs(),
+                path
+            ),
+            InstallError::UnsupportedPlatform(message)
+            | InstallError::MissingRuntime(message)
+            | InstallError::VersionNotFound(message)
+            | InstallError::Offline(message)
+            | InstallError::Unverified(message)
+            | InstallError::Config(message)
+            | InstallError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for InstallError {
+    fn from(message: String) -> Self {
+        InstallError::Other(message)
+    }
+}
+
+impl From<InstallError> for String {
+    fn from(error: InstallError) -> Self {
+        error.user_message()
+    }
+}
+
+/// First number in an error message that looks like an HTTP error status, when the
+/// message mentions a status at all
+fn http_status(message: &str) -> Option<u16> {
+    if !message.to_ascii_lowercase().contains("status") {
+        return None;
+    }
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|digits| digits.len() == 3)
+        .filter_map(|digits| digits.parse().ok())
+        .find(|status| (400..600).contains(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_retryable_fetch_errors() {
+        let error = |message| InstallError::from_fetch("https://feed.example/a.nupkg", message);
+
+        let not_found = error("status code 404 Not Found");
+        assert_eq!(
+            not_found,
+            InstallError::HttpStatus {
+                url: "https://feed.example/a.nupkg".to_string(),
+                status: 404
+            }
+        );
+        assert!(!not_found.is_retryable());
+        assert_eq!(not_found.backoff(1), Duration::ZERO);
+
+        let unavailable = error("unexpected status: 503");
+        assert!(unavailable.is_retryable());
+        assert_eq!(unavailable.backoff(1), Duration::from_secs(1));
+        assert_eq!(unavailable.backoff(3), Duration::from_secs(4));
+        assert_eq!(unavailable.backoff(10), MAX_BACKOFF);
+
+        let reset = error("connection reset by peer after 500 bytes");
+        assert!(matches!(reset, InstallError::Network { .. }));
+        assert!(reset.is_retryable());
+
+        assert_eq!(error("status 429").backoff(1), Duration::from_secs(10));
+
+        assert!(!InstallError::ChecksumMismatch("checksum mismatch".to_string()).is_retryable());
+    }
+}
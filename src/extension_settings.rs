@@ -0,0 +1,329 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use zed_extension_api::{self as zed, serde_json::Value, settings::LspSettings, Result};
+
+use crate::dotnet::RollForward;
+use crate::update_state;
+use crate::version::VersionReq;
+use crate::version_config::{
+    self, HttpAuth, NugetFeed, VersionDirConfig, VersionSource, CSHARP_LANGUAGE_SERVER, NETCOREDBG,
+};
+
+/// Extension settings, read from the language server's `settings` block:
+///
+/// ```json
+/// "lsp": {
+///   "csharp-language-server": {
+///     "settings": {
+///       "version": "0.16.0",
+///       "offline": false,
+///       "update_check_interval": "daily",
+///       "retain_versions": 1,
+///       "roll_forward": "Major",
+///       "archive": "csharp-ls.0.16.0.nupkg",
+///       "source": {
+///         "type": "nuget",
+///         "url": "https://nuget.corp.example/v3/index.json",
+///         "token_env": "CORP_NUGET_TOKEN"
+///       },
+///       "debugger": { "version": ">=3.1, <3.2", "archive": null, "source": null },
+///       "tools": {
+///         "netcoredbg": { "assets": { "linux-riscv64": "netcoredbg-linux-riscv64.tar.gz" } }
+///       }
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExtensionSettings {
+    /// Exact version or version range of csharp-language-server to install
+    pub version: Option<String>,
+    /// Never touch the network; only use installed versions or side-loaded archives
+    pub offline: bool,
+    /// How often to ask sources for new versions (`always`, `daily`, `12h`, ...); daily by default
+    pub update_check_interval: Option<String>,
+    /// Previous versions of each tool to keep installed after an update; 1 by default
+    pub retain_versions: Option<usize>,
+    /// Roll-forward policy to start csharp-language-server with, overriding its runtimeconfig
+    pub roll_forward: Option<String>,
+    /// Install csharp-language-server from this local `.nupkg` instead of downloading it
+    pub archive: Option<String>,
+    /// Where to look up and download csharp-language-server instead of nuget.org
+    pub source: Option<SourceSettings>,
+    /// Settings for the netcoredbg debug adapter
+    pub debugger: DebuggerSettings,
+    /// Overrides merged into the built-in tool manifests, by tool name
+    pub tools: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DebuggerSettings {
+    /// Exact version or version range of netcoredbg to install
+    pub version: Option<String>,
+    /// Install netcoredbg from this local `.zip`/`.tar.gz` instead of downloading it
+    pub archive: Option<String>,
+    /// Where to look up and download netcoredbg instead of github.com
+    pub source: Option<SourceSettings>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// A NuGet v3 feed, by service index or flat container URL
+    Nuget,
+    /// GitHub releases, on github.com or a GitHub Enterprise API URL
+    Github,
+    /// A plain HTTP directory mirroring the release archives
+    Http,
+    /// A folder of archives inside the extension work directory
+    Directory,
+}
+
+/// A custom download source for one tool
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceSettings {
+    #[serde(rename = "type")]
+    pub kind: SourceKind,
+    /// Feed, API or directory URL; optional only for github.com
+    pub url: Option<String>,
+    /// Folder of a directory source, relative to the extension work directory
+    pub path: Option<String>,
+    /// GitHub repository, defaults to the upstream one
+    pub repo: Option<String>,
+    /// NuGet package id, defaults to the upstream one
+    pub package: Option<String>,
+    /// Token to authenticate with
+    pub token: Option<String>,
+    /// Environment variable holding the token, preferred over `token`
+    pub token_env: Option<String>,
+    /// Header to send the token in as-is; `Authorization: Bearer <token>` when unset
+    pub header: Option<String>,
+    /// For http sources, download URL with `{url}`, `{version}` and `{asset}`
+    /// placeholders; `<url>/<version>/<asset>` when unset
+    pub url_template: Option<String>,
+    /// Fall back to the default source when this one can't be reached
+    #[serde(default)]
+    pub fallback: bool,
+}
+
+impl SourceSettings {
+    /// Point `config` at this source. `key` is the settings key, for error messages.
+    pub fn apply(
+        &self,
+        config: VersionDirConfig,
+        env: &[(String, String)],
+        key: &str,
+    ) -> Result<VersionDirConfig> {
+        let invalid = |message: String| {
+            format!(
+                "invalid lsp.{}.settings.{}: {}",
+                crate::LANGUAGE_SERVER_NAME,
+                key,
+                message
+            )
+        };
+        let url = || {
+            self.url
+                .clone()
+                .ok_or_else(|| invalid("nuget and http sources need a url".to_string()))
+        };
+
+        let token = match &self.token_env {
+            Some(name) => Some(
+                env.iter()
+                    .find(|(var, _)| var == name)
+                    .map(|(_, value)| value.clone())
+                    .ok_or_else(|| invalid(format!("environment variable {} is not set", name)))?,
+            ),
+            None => self.token.clone(),
+        };
+        let auth = token.map(|token| match &self.header {
+            Some(header) => HttpAuth {
+                header: header.clone(),
+                value: token,
+            },
+            None => HttpAuth::bearer(&token),
+        });
+
+        let source = match (self.kind, &config.version_source) {
+            (SourceKind::Github, upstream) => VersionSource::GithubRelease {
+                api_url: self.url.clone(),
+                auth,
+                repo: match (&self.repo, upstream) {
+                    (Some(repo), _) => repo.clone(),
+                    (None, VersionSource::GithubRelease { repo, .. }) => repo.clone(),
+                    (None, _) => return Err(invalid("a github source needs a repo".to_string())),
+                },
+            },
+            (SourceKind::Nuget, upstream) => VersionSource::Nuget {
+                feeds: vec![NugetFeed { url: url()?, auth }],
+                package_id: match (&self.package, upstream) {
+                    (Some(package), _) => package.clone(),
+                    (None, VersionSource::Nuget { package_id, .. }) => package_id.clone(),
+                    (None, _) => return Err(invalid("a nuget source needs a package".to_string())),
+                },
+            },
+            (SourceKind::Http, _) => VersionSource::HttpDirectory {
+                base_url: url()?,
+                url_template: self.url_template.clone(),
+                auth,
+            },
+            (SourceKind::Directory, _) => VersionSource::Directory {
+                path: self
+                    .path
+                    .clone()
+                    .ok_or_else(|| invalid("a directory source needs a path".to_string()))?,
+            },
+        };
+        let source = match self.fallback {
+            true => VersionSource::Fallback {
+                primary: Box::new(source),
+                fallback: Box::new(config.version_source.clone()),
+            },
+            false => source,
+        };
+
+        Ok(config.with_source(source))
+    }
+}
+
+impl ExtensionSettings {
+    pub fn for_worktree(worktree: &zed::Worktree) -> Result<Self> {
+        let settings = LspSettings::for_worktree(crate::LANGUAGE_SERVER_NAME, worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.settings);
+
+        match settings {
+            Some(value) => zed::serde_json::from_value(value).map_err(|e| {
+                format!(
+                    "invalid settings for lsp.{}.settings: {}",
+                    crate::LANGUAGE_SERVER_NAME,
+                    e
+                )
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Parsed csharp-language-server version requirement
+    pub fn language_server_version(&self) -> Result<Option<VersionReq>> {
+        Self::parse_requirement(self.version.as_deref(), "version")
+    }
+
+    /// Parsed netcoredbg version requirement
+    pub fn debugger_version(&self) -> Result<Option<VersionReq>> {
+        Self::parse_requirement(self.debugger.version.as_deref(), "debugger.version")
+    }
+
+    /// Parsed update check interval, daily unless configured
+    pub fn update_check_interval(&self) -> Result<Duration> {
+        update_state::parse_interval(self.update_check_interval.as_deref().unwrap_or("daily"))
+            .map_err(|e| {
+                format!(
+                    "invalid lsp.{}.settings.update_check_interval: {}",
+                    crate::LANGUAGE_SERVER_NAME,
+                    e
+                )
+            })
+    }
+
+    pub fn retain_versions(&self) -> usize {
+        self.retain_versions.unwrap_or(1)
+    }
+
+    /// Parsed roll-forward policy for csharp-language-server, if configured
+    pub fn roll_forward(&self) -> Result<Option<RollForward>> {
+        Self::non_empty(self.roll_forward.as_deref())
+            .map(|value| {
+                RollForward::parse(&value).ok_or_else(|| {
+                    format!(
+                        "invalid lsp.{}.settings.roll_forward: unknown policy {}, expected one of Disable, LatestPatch, Minor, LatestMinor, Major, LatestMajor",
+                        crate::LANGUAGE_SERVER_NAME,
+                        value
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// csharp-language-server configuration, with its manifest overrides applied
+    pub fn language_server_config(&self) -> Result<VersionDirConfig> {
+        self.tool_config(CSHARP_LANGUAGE_SERVER)
+    }
+
+    /// netcoredbg configuration, with its manifest overrides applied
+    pub fn debugger_config(&self) -> Result<VersionDirConfig> {
+        self.tool_config(NETCOREDBG)
+    }
+
+    fn tool_config(&self, name: &str) -> Result<VersionDirConfig> {
+        version_config::tool_config(name, self.tools.get(name)).map_err(|e| {
+            format!(
+                "invalid lsp.{}.settings.tools.{}: {}",
+                crate::LANGUAGE_SERVER_NAME,
+                name,
+                e
+            )
+        })
+    }
+
+    /// Apply the csharp-language-server source override, if configured
+    pub fn language_server_source(
+        &self,
+        config: VersionDirConfig,
+        env: &[(String, String)],
+    ) -> Result<VersionDirConfig> {
+        match &self.source {
+            Some(source) => source.apply(config, env, "source"),
+            None => Ok(config),
+        }
+    }
+
+    /// Apply the netcoredbg source override, if configured
+    pub fn debugger_source(
+        &self,
+        config: VersionDirConfig,
+        env: &[(String, String)],
+    ) -> Result<VersionDirConfig> {
+        match &self.debugger.source {
+            Some(source) => source.apply(config, env, "debugger.source"),
+            None => Ok(config),
+        }
+    }
+
+    /// Side-loaded csharp-language-server archive, if configured
+    pub fn language_server_archive(&self) -> Option<String> {
+        Self::non_empty(self.archive.as_deref())
+    }
+
+    /// Side-loaded netcoredbg archive, if configured
+    pub fn debugger_archive(&self) -> Option<String> {
+        Self::non_empty(self.debugger.archive.as_deref())
+    }
+
+    fn non_empty(value: Option<&str>) -> Option<String> {
+        value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+
+    fn parse_requirement(value: Option<&str>, key: &str) -> Result<Option<VersionReq>> {
+        value
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| {
+                VersionReq::parse(value).map_err(|e| {
+                    format!(
+                        "invalid lsp.{}.settings.{}: {}",
+                        crate::LANGUAGE_SERVER_NAME,
+                        key,
+                        e
+                    )
+                })
+            })
+            .transpose()
+    }
+}
@@ -0,0 +1,450 @@
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::time::SystemTime;
+use zed_extension_api as zed;
+
+use crate::path_utils;
+
+/// What is known about a file or directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    /// Size in bytes; zero for directories
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Metadata {
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+}
+
+/// A seekable reader, for archives the zip reader needs random access to
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// The extension work directory, where installs, locks and state files live. Relative
+/// paths are relative to it; absolute paths must point inside it.
+pub trait FileSystem: Send + Sync {
+    /// Absolute form of a path in the work directory, as handed to Zed
+    fn absolute(&self, path: &str) -> String;
+    fn metadata(&self, path: &str) -> io::Result<Metadata>;
+    /// Names of the entries of a directory, in no particular order
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>>;
+    fn open(&self, path: &str) -> io::Result<Box<dyn ReadSeek>>;
+    /// Create a file, or truncate an existing one
+    fn create(&self, path: &str) -> io::Result<Box<dyn Write>>;
+    /// Open a file for appending, creating it when missing
+    fn append(&self, path: &str) -> io::Result<Box<dyn Write>>;
+    /// Create a file with `contents`, failing with `AlreadyExists` if it exists. Atomic,
+    /// so it can serve as a lock between processes.
+    fn create_new(&self, path: &str, contents: &[u8]) -> io::Result<()>;
+    fn create_dir_all(&self, path: &str) -> io::Result<()>;
+    fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+    fn remove_file(&self, path: &str) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &str) -> io::Result<()>;
+    /// Set the executable bit on a file; a no-op where there is no such bit
+    fn make_executable(&self, path: &str) -> Result<(), String>;
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn write(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.create(path)?.write_all(contents)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        self.metadata(path).is_ok_and(|stat| stat.is_dir)
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.metadata(path).is_ok_and(|stat| stat.is_file())
+    }
+}
+
+/// The real work directory, which is the extension's current directory
+pub struct WorkDir;
+
+impl FileSystem for WorkDir {
+    fn absolute(&self, path: &str) -> String {
+        path_utils::normalize_path_to_absolute(path)
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let stat = fs::metadata(path)?;
+        Ok(Metadata {
+            is_dir: stat.is_dir(),
+            len: if stat.is_dir() { 0 } else { stat.len() },
+            modified: stat.modified().ok(),
+        })
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+            .collect()
+    }
+
+    fn open(&self, path: &str) -> io::Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn create(&self, path: &str) -> io::Result<Box<dyn Write>> {
+        Ok(Box::new(fs::File::create(path)?))
+    }
+
+    fn append(&self, path: &str) -> io::Result<Box<dyn Write>> {
+        Ok(Box::new(
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?,
+        ))
+    }
+
+    fn create_new(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(contents)
+    }
+
+    fn create_dir_all(&self, path: &str) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &str) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &str) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    /// Goes through the host: Zed can't see wasm-side permission changes
+    fn make_executable(&self, path: &str) -> Result<(), String> {
+        if matches!(zed::current_platform().0, zed::Os::Windows) {
+            return Ok(());
+        }
+        zed::make_file_executable(path)
+    }
+}
+
+#[cfg(test)]
+pub use memory::MemoryFileSystem;
+
+#[cfg(test)]
+mod memory {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    /// Absolute path of the in-memory work directory
+    const ROOT: &str = "/work";
+
+    #[derive(Debug, Clone)]
+    enum Node {
+        Dir,
+        File {
+            contents: Vec<u8>,
+            modified: SystemTime,
+            executable: bool,
+        },
+    }
+
+    /// An in-memory work directory for tests. Clones share the same files.
+    #[derive(Clone, Default)]
+    pub struct MemoryFileSystem {
+        nodes: Arc<Mutex<BTreeMap<String, Node>>>,
+    }
+
+    fn not_found(path: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path))
+    }
+
+    /// `path` relative to the work directory, without `.` components; empty for the root
+    fn normalize(path: &str) -> String {
+        let path = path.strip_prefix(ROOT).unwrap_or(path);
+        path.split('/')
+            .filter(|part| !part.is_empty() && *part != ".")
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn parent(path: &str) -> &str {
+        path.rsplit_once('/').map_or("", |(parent, _)| parent)
+    }
+
+    fn is_within(path: &str, dir: &str) -> bool {
+        path == dir || path.starts_with(&format!("{}/", dir))
+    }
+
+    impl MemoryFileSystem {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn exists(&self, path: &str) -> bool {
+            let path = normalize(path);
+            path.is_empty() || self.nodes.lock().unwrap().contains_key(&path)
+        }
+
+        pub fn is_executable(&self, path: &str) -> bool {
+            matches!(
+                self.nodes.lock().unwrap().get(&normalize(path)),
+                Some(Node::File {
+                    executable: true,
+                    ..
+                })
+            )
+        }
+
+        /// Write a file, creating its parent directories
+        pub fn put(&self, path: &str, contents: &[u8]) {
+            let path = normalize(path);
+            let mut nodes = self.nodes.lock().unwrap();
+            let mut dir = parent(&path);
+            while !dir.is_empty() {
+                nodes.insert(dir.to_string(), Node::Dir);
+                dir = parent(dir);
+            }
+            nodes.insert(
+                path,
+                Node::File {
+                    contents: contents.to_vec(),
+                    modified: SystemTime::now(),
+                    executable: false,
+                },
+            );
+        }
+
+        /// Make a file look last modified `age` ago
+        pub fn backdate(&self, path: &str, age: std::time::Duration) {
+            if let Some(Node::File { modified, .. }) =
+                self.nodes.lock().unwrap().get_mut(&normalize(path))
+            {
+                *modified = SystemTime::now() - age;
+            }
+        }
+
+        /// Open `path` for writing, keeping its contents when `append` is set
+        fn writer(&self, path: &str, append: bool) -> io::Result<Box<dyn Write>> {
+            let path = normalize(path);
+            let mut nodes = self.nodes.lock().unwrap();
+            let parent_dir = parent(&path);
+            if !parent_dir.is_empty() && !matches!(nodes.get(parent_dir), Some(Node::Dir)) {
+                return Err(not_found(parent_dir));
+            }
+            match nodes.get_mut(&path) {
+                Some(Node::Dir) => {
+                    return Err(io::Error::other(format!("{} is a directory", path)));
+                }
+                Some(Node::File { .. }) if append => {}
+                Some(Node::File { contents, .. }) => contents.clear(),
+                None => {
+                    nodes.insert(
+                        path.clone(),
+                        Node::File {
+                            contents: Vec::new(),
+                            modified: SystemTime::now(),
+                            executable: false,
+                        },
+                    );
+                }
+            }
+            Ok(Box::new(MemoryWriter {
+                nodes: self.nodes.clone(),
+                path,
+            }))
+        }
+    }
+
+    struct MemoryWriter {
+        nodes: Arc<Mutex<BTreeMap<String, Node>>>,
+        path: String,
+    }
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.nodes.lock().unwrap().get_mut(&self.path) {
+                Some(Node::File {
+                    contents, modified, ..
+                }) => {
+                    contents.extend_from_slice(buf);
+                    *modified = SystemTime::now();
+                    Ok(buf.len())
+                }
+                _ => Err(not_found(&self.path)),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn absolute(&self, path: &str) -> String {
+            match normalize(path).as_str() {
+                "" => ROOT.to_string(),
+                path => format!("{}/{}", ROOT, path),
+            }
+        }
+
+        fn metadata(&self, path: &str) -> io::Result<Metadata> {
+            let path = normalize(path);
+            if path.is_empty() {
+                return Ok(Metadata {
+                    is_dir: true,
+                    len: 0,
+                    modified: None,
+                });
+            }
+            match self.nodes.lock().unwrap().get(&path) {
+                Some(Node::Dir) => Ok(Metadata {
+                    is_dir: true,
+                    len: 0,
+                    modified: None,
+                }),
+                Some(Node::File {
+                    contents, modified, ..
+                }) => Ok(Metadata {
+                    is_dir: false,
+                    len: contents.len() as u64,
+                    modified: Some(*modified),
+                }),
+                None => Err(not_found(&path)),
+            }
+        }
+
+        fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+            let path = normalize(path);
+            if !path.is_empty() && !self.is_dir(&path) {
+                return Err(not_found(&path));
+            }
+            let nodes = self.nodes.lock().unwrap();
+            Ok(nodes
+                .keys()
+                .filter(|candidate| parent(candidate) == path && !candidate.is_empty())
+                .map(|candidate| {
+                    candidate
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or(candidate)
+                        .to_string()
+                })
+                .collect())
+        }
+
+        fn open(&self, path: &str) -> io::Result<Box<dyn ReadSeek>> {
+            let path = normalize(path);
+            match self.nodes.lock().unwrap().get(&path) {
+                Some(Node::File { contents, .. }) => Ok(Box::new(Cursor::new(contents.clone()))),
+                Some(Node::Dir) => Err(io::Error::other(format!("{} is a directory", path))),
+                None => Err(not_found(&path)),
+            }
+        }
+
+        fn create(&self, path: &str) -> io::Result<Box<dyn Write>> {
+            self.writer(path, false)
+        }
+
+        fn append(&self, path: &str) -> io::Result<Box<dyn Write>> {
+            self.writer(path, true)
+        }
+
+        fn create_new(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+            if self.exists(path) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists", path),
+                ));
+            }
+            self.writer(path, false)?.write_all(contents)
+        }
+
+        fn create_dir_all(&self, path: &str) -> io::Result<()> {
+            let path = normalize(path);
+            let mut nodes = self.nodes.lock().unwrap();
+            let mut dir = path.as_str();
+            while !dir.is_empty() {
+                if let Some(Node::File { .. }) = nodes.get(dir) {
+                    return Err(io::Error::other(format!("{} is a file", dir)));
+                }
+                nodes.insert(dir.to_string(), Node::Dir);
+                dir = parent(dir);
+            }
+            Ok(())
+        }
+
+        fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+            let (from, to) = (normalize(from), normalize(to));
+            let mut nodes = self.nodes.lock().unwrap();
+            if !nodes.contains_key(&from) {
+                return Err(not_found(&from));
+            }
+            if matches!(nodes.get(&to), Some(Node::Dir)) {
+                return Err(io::Error::other(format!("{} exists", to)));
+            }
+            let moved: Vec<String> = nodes
+                .keys()
+                .filter(|path| is_within(path, &from))
+                .cloned()
+                .collect();
+            for path in moved {
+                let node = nodes.remove(&path).unwrap();
+                nodes.insert(format!("{}{}", to, &path[from.len()..]), node);
+            }
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &str) -> io::Result<()> {
+            let path = normalize(path);
+            let mut nodes = self.nodes.lock().unwrap();
+            match nodes.get(&path) {
+                Some(Node::File { .. }) => {
+                    nodes.remove(&path);
+                    Ok(())
+                }
+                Some(Node::Dir) => Err(io::Error::other(format!("{} is a directory", path))),
+                None => Err(not_found(&path)),
+            }
+        }
+
+        fn remove_dir_all(&self, path: &str) -> io::Result<()> {
+            let path = normalize(path);
+            let mut nodes = self.nodes.lock().unwrap();
+            if !matches!(nodes.get(&path), Some(Node::Dir)) {
+                return Err(not_found(&path));
+            }
+            nodes.retain(|candidate, _| !is_within(candidate, &path));
+            Ok(())
+        }
+
+        fn make_executable(&self, path: &str) -> Result<(), String> {
+            match self.nodes.lock().unwrap().get_mut(&normalize(path)) {
+                Some(Node::File { executable, .. }) => {
+                    *executable = true;
+                    Ok(())
+                }
+                _ => Err(format!("{} not found", path)),
+            }
+        }
+    }
+}
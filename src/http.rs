@@ -0,0 +1,345 @@
+use std::time::Duration;
+use zed_extension_api::http_client::{self, HttpRequest, HttpResponse};
+use zed_extension_api::{self as zed, GithubRelease};
+
+/// A response body arriving in chunks
+pub trait ChunkStream {
+    /// The next chunk, or None once the body is complete
+    fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, String>;
+}
+
+impl ChunkStream for http_client::HttpResponseStream {
+    fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        http_client::HttpResponseStream::next_chunk(self)
+    }
+}
+
+/// How downloads reach the network. Errors are the client's text, classified by
+/// `InstallError::from_fetch`. Zed reports error statuses as errors, and only whole
+/// responses come with headers.
+pub trait HttpClient: Send + Sync {
+    fn fetch(&self, request: &HttpRequest) -> Result<HttpResponse, String>;
+
+    fn fetch_stream(&self, request: &HttpRequest) -> Result<Box<dyn ChunkStream>, String>;
+
+    /// A github.com release through Zed's GitHub client: the one tagged `tag`, or the
+    /// latest one that isn't a prerelease when `tag` is None
+    fn github_release(&self, repo: &str, tag: Option<&str>) -> Result<GithubRelease, String>;
+
+    /// Wait before retrying a failed request
+    fn wait(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Zed's HTTP client
+pub struct ZedHttpClient;
+
+impl HttpClient for ZedHttpClient {
+    fn fetch(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        http_client::fetch(request)
+    }
+
+    fn fetch_stream(&self, request: &HttpRequest) -> Result<Box<dyn ChunkStream>, String> {
+        Ok(Box::new(http_client::fetch_stream(request)?))
+    }
+
+    fn github_release(&self, repo: &str, tag: Option<&str>) -> Result<GithubRelease, String> {
+        match tag {
+            Some(tag) => zed::github_release_by_tag_name(repo, tag),
+            None => zed::latest_github_release(
+                repo,
+                zed::GithubReleaseOptions {
+                    require_assets: false,
+                    pre_release: false,
+                },
+            ),
+        }
+    }
+}
+
+/// A GET request for `url` that follows redirects
+pub fn get_request(url: &str, headers: Vec<(String, String)>) -> HttpRequest {
+    HttpRequest {
+        method: http_client::HttpMethod::Get,
+        url: url.to_string(),
+        headers,
+        body: None,
+        redirect_policy: http_client::RedirectPolicy::FollowAll,
+    }
+}
+
+/// First byte and full size, when known, from the `Content-Range` header of a partial
+/// response; None when the response isn't partial
+pub fn content_range(headers: &[(String, String)]) -> Option<(u64, Option<u64>)> {
+    let value = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-range"))?
+        .1
+        .trim();
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+#[cfg(test)]
+pub use fake::FakeHttpClient;
+
+#[cfg(test)]
+mod fake {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// What the fake answers a request with
+    #[derive(Debug, Clone)]
+    enum Response {
+        Body(Vec<u8>),
+        /// Fail to connect
+        Error(String),
+        /// Send the first bytes of the body, then drop the connection
+        Truncated(Vec<u8>, usize),
+    }
+
+    #[derive(Default)]
+    struct State {
+        /// Responses by URL, consumed in order; the last one repeats
+        responses: HashMap<String, Vec<Response>>,
+        /// URL and headers of every request made
+        requests: Vec<(String, Vec<(String, String)>)>,
+        waited: Duration,
+        /// Answer ranged requests with the whole body, like a server without range support
+        ignore_ranges: bool,
+        /// github.com releases by repository, oldest first
+        github_releases: HashMap<String, Vec<GithubRelease>>,
+    }
+
+    /// An in-memory HTTP server for tests. Clones share the same responses. Waits
+    /// between retries return immediately and are only added up.
+    #[derive(Clone, Default)]
+    pub struct FakeHttpClient {
+        state: Arc<Mutex<State>>,
+    }
+
+    impl FakeHttpClient {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn push(&self, url: &str, response: Response) {
+            let mut state = self.state.lock().unwrap();
+            state
+                .responses
+                .entry(url.to_string())
+                .or_default()
+                .push(response);
+        }
+
+        /// Answer `url` with `body`
+        pub fn serve(&self, url: &str, body: &[u8]) {
+            self.push(url, Response::Body(body.to_vec()));
+        }
+
+        /// Fail requests to `url` with a client error such as `status code 503`
+        pub fn fail(&self, url: &str, message: &str) {
+            self.push(url, Response::Error(message.to_string()));
+        }
+
+        /// Answer `url` with the first `sent` bytes of `body`, then drop the connection
+        pub fn truncate(&self, url: &str, body: &[u8], sent: usize) {
+            self.push(url, Response::Truncated(body.to_vec(), sent));
+        }
+
+        /// Publish a github.com release of `repo` to Zed's GitHub client, with assets as
+        /// (name, download URL); the last one published is the latest
+        pub fn publish_github_release(&self, repo: &str, tag: &str, assets: &[(&str, &str)]) {
+            let release = GithubRelease {
+                version: tag.to_string(),
+                assets: assets
+                    .iter()
+                    .map(|(name, url)| zed::GithubReleaseAsset {
+                        name: name.to_string(),
+                        download_url: url.to_string(),
+                    })
+                    .collect(),
+            };
+            let mut state = self.state.lock().unwrap();
+            state
+                .github_releases
+                .entry(repo.to_string())
+                .or_default()
+                .push(release);
+        }
+
+        /// Ignore `Range` headers from now on
+        pub fn ignore_ranges(&self) {
+            self.state.lock().unwrap().ignore_ranges = true;
+        }
+
+        /// URLs requested so far, in order. Lookups through the GitHub client are listed
+        /// as the github.com page of the release (`.../releases/latest`, `.../releases/tag/<tag>`).
+        pub fn requests(&self) -> Vec<String> {
+            let state = self.state.lock().unwrap();
+            state.requests.iter().map(|(url, _)| url.clone()).collect()
+        }
+
+        /// Value of `header` in the `index`th request
+        pub fn header(&self, index: usize, header: &str) -> Option<String> {
+            let state = self.state.lock().unwrap();
+            state
+                .requests
+                .get(index)?
+                .1
+                .iter()
+                .find_map(|(name, value)| name.eq_ignore_ascii_case(header).then(|| value.clone()))
+        }
+
+        /// Total time spent waiting between retries
+        pub fn waited(&self) -> Duration {
+            self.state.lock().unwrap().waited
+        }
+    }
+
+    /// Body chunks, then an optional error
+    struct FakeStream {
+        chunks: Vec<Vec<u8>>,
+        error: Option<String>,
+    }
+
+    impl ChunkStream for FakeStream {
+        fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+            if !self.chunks.is_empty() {
+                return Ok(Some(self.chunks.remove(0)));
+            }
+            match self.error.take() {
+                Some(error) => Err(error),
+                None => Ok(None),
+            }
+        }
+    }
+
+    impl FakeHttpClient {
+        /// Record `request` and pick its response: the part of the body it asks for, its
+        /// headers, and an error to end the body with
+        fn respond(&self, request: &HttpRequest) -> Result<Reply, String> {
+            let mut state = self.state.lock().unwrap();
+            state
+                .requests
+                .push((request.url.clone(), request.headers.clone()));
+            let ignore_ranges = state.ignore_ranges;
+            let responses = state
+                .responses
+                .get_mut(&request.url)
+                .ok_or_else(|| format!("status code 404 for {}", request.url))?;
+            let response = if responses.len() > 1 {
+                responses.remove(0)
+            } else {
+                responses[0].clone()
+            };
+            let (body, error) = match response {
+                Response::Body(body) => (body, None),
+                Response::Error(message) => return Err(message),
+                Response::Truncated(body, sent) => (
+                    body[..sent].to_vec(),
+                    Some("connection reset by peer".to_string()),
+                ),
+            };
+
+            // Honor `Range: bytes=<start>-[<end>]` like a server that supports resuming
+            let range = request
+                .headers
+                .iter()
+                .find(|(name, _)| name == "Range")
+                .and_then(|(_, value)| value.strip_prefix("bytes=")?.split_once('-'))
+                .filter(|_| !ignore_ranges);
+            let Some((start, end)) = range else {
+                return Ok(Reply {
+                    headers: Vec::new(),
+                    body,
+                    error,
+                });
+            };
+            let start: usize = start.parse().unwrap();
+            if start >= body.len() {
+                return Err("status code 416 Range Not Satisfiable".to_string());
+            }
+            let end = end
+                .parse::<usize>()
+                .map_or(body.len(), |end| (end + 1).min(body.len()));
+            Ok(Reply {
+                headers: vec![(
+                    "content-range".to_string(),
+                    format!("bytes {}-{}/{}", start, end - 1, body.len()),
+                )],
+                body: body[start..end].to_vec(),
+                error,
+            })
+        }
+    }
+
+    struct Reply {
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+        error: Option<String>,
+    }
+
+    impl HttpClient for FakeHttpClient {
+        fn fetch(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+            let reply = self.respond(request)?;
+            match reply.error {
+                Some(error) => Err(error),
+                None => Ok(HttpResponse {
+                    headers: reply.headers,
+                    body: reply.body,
+                }),
+            }
+        }
+
+        fn fetch_stream(&self, request: &HttpRequest) -> Result<Box<dyn ChunkStream>, String> {
+            let reply = self.respond(request)?;
+            Ok(Box::new(FakeStream {
+                chunks: reply.body.chunks(1024).map(<[u8]>::to_vec).collect(),
+                error: reply.error,
+            }))
+        }
+
+        fn github_release(&self, repo: &str, tag: Option<&str>) -> Result<GithubRelease, String> {
+            let page = match tag {
+                Some(tag) => format!("https://github.com/{}/releases/tag/{}", repo, tag),
+                None => format!("https://github.com/{}/releases/latest", repo),
+            };
+            let mut state = self.state.lock().unwrap();
+            state.requests.push((page.clone(), Vec::new()));
+            let releases = state.github_releases.get(repo).map(Vec::as_slice);
+            match tag {
+                Some(tag) => releases
+                    .and_then(|releases| releases.iter().find(|release| release.version == tag)),
+                None => releases.and_then(<[GithubRelease]>::last),
+            }
+            .cloned()
+            .ok_or_else(|| format!("status code 404 for {}", page))
+        }
+
+        fn wait(&self, duration: Duration) {
+            self.state.lock().unwrap().waited += duration;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_ranges() {
+        let headers = |value: &str| vec![("Content-Range".to_string(), value.to_string())];
+
+        assert_eq!(
+            content_range(&headers("bytes 19-19/4096")),
+            Some((19, Some(4096)))
+        );
+        assert_eq!(content_range(&headers("bytes 0-99/*")), Some((0, None)));
+        assert_eq!(content_range(&headers("bytes */4096")), None);
+        assert_eq!(content_range(&[]), None);
+    }
+}
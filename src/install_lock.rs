@@ -0,0 +1,316 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::InstallError;
+use crate::file_system::FileSystem;
+use crate::logger;
+use crate::progress::{InstallProgress, InstallStatus};
+
+/// A lock older than this is left over from a crashed or killed install
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+/// How long to wait for another instance's install before giving up
+pub const MAX_LOCK_WAIT: Duration = Duration::from_secs(15 * 60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How often a held lock's timestamp is renewed, well within `STALE_LOCK_AGE`
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Cross-process lock on installing one version directory, held for as long as the
+/// value lives. Zed windows run separate extension instances that share the work
+/// directory, so the lock is a file created with `create_new`. It holds a token unique to
+/// its owner, and is only renewed or removed while it still holds that token.
+pub struct InstallLock {
+    fs: Arc<dyn FileSystem>,
+    path: String,
+    token: String,
+    refreshed: Cell<Instant>,
+}
+
+impl InstallLock {
+    /// Lock file of a version directory
+    pub fn path(version_dir: &str) -> String {
+        format!(".{}.lock", version_dir)
+    }
+
+    /// Take the lock, waiting up to `MAX_LOCK_WAIT` for an install in progress in another
+    /// instance to finish. Locks older than `STALE_LOCK_AGE` are broken.
+    pub fn acquire(
+        fs: Arc<dyn FileSystem>,
+        version_dir: &str,
+        fn_name: &str,
+    ) -> Result<Self, InstallError> {
+        Self::acquire_within(fs, version_dir, fn_name, MAX_LOCK_WAIT)
+    }
+
+    fn acquire_within(
+        fs: Arc<dyn FileSystem>,
+        version_dir: &str,
+        fn_name: &str,
+        max_wait: Duration,
+    ) -> Result<Self, InstallError> {
+        let path = Self::path(version_dir);
+        let token = unique_token();
+        let started = Instant::now();
+        let mut waited = false;
+
+        loop {
+            match fs.create_new(&path, &contents(&token)) {
+                Ok(()) => {
+                    return Ok(Self {
+                        fs,
+                        path,
+                        token,
+                        refreshed: Cell::new(Instant::now()),
+                    })
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let age = fs
+                        .metadata(&path)
+                        .ok()
+                        .and_then(|metadata| metadata.modified)
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age > STALE_LOCK_AGE) {
+                        logger::Logger::warn(&format!(
+                            "{}: breaking stale install lock {}",
+                            fn_name, path
+                        ));
+                        break_stale(fs.as_ref(), &path, &token);
+                        continue;
+                    }
+
+                    if started.elapsed() >= max_wait {
+                        return Err(InstallError::Locked {
+                            path,
+                            waited: started.elapsed(),
+                        });
+                    }
+                    if !waited {
+                        logger::Logger::info(&format!(
+                            "{}: waiting for another instance to finish installing {}",
+                            fn_name, version_dir
+                        ));
+                        waited = true;
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(e) => return Err(InstallError::io(&path, &e)),
+            }
+        }
+    }
+
+    /// Renew the lock's timestamp, so other instances don't take a long install for a
+    /// crashed one. Cheap to call often: the file is only rewritten every `REFRESH_INTERVAL`.
+    pub fn refresh(&self) {
+        if self.refreshed.get().elapsed() < REFRESH_INTERVAL {
+            return;
+        }
+        if !self.is_owned() {
+            logger::Logger::warn(&format!(
+                "refresh: install lock {} was taken over by another instance",
+                self.path
+            ));
+        } else if let Err(e) = self.fs.write(&self.path, &contents(&self.token)) {
+            logger::Logger::warn(&format!(
+                "refresh: failed to renew install lock {}: {}",
+                self.path, e
+            ));
+        }
+        self.refreshed.set(Instant::now());
+    }
+
+    /// Whether the lock file still holds this lock's token
+    fn is_owned(&self) -> bool {
+        self.fs
+            .read_to_string(&self.path)
+            .is_ok_and(|contents| owner(&contents) == Some(self.token.as_str()))
+    }
+
+    /// `progress`, refreshing the lock whenever the install makes headway
+    pub fn heartbeat<'a>(&'a self, progress: &'a dyn InstallProgress) -> Heartbeat<'a> {
+        Heartbeat {
+            lock: self,
+            progress,
+        }
+    }
+}
+
+/// Progress that keeps an `InstallLock` fresh, see `InstallLock::heartbeat`
+pub struct Heartbeat<'a> {
+    lock: &'a InstallLock,
+    progress: &'a dyn InstallProgress,
+}
+
+impl InstallProgress for Heartbeat<'_> {
+    fn report(&self, status: &InstallStatus) {
+        self.lock.refresh();
+        self.progress.report(status);
+    }
+
+    fn advance(&self) {
+        self.lock.refresh();
+        self.progress.advance();
+    }
+}
+
+/// Contents of a lock file: when it was last renewed, for anyone inspecting the work
+/// directory, and the owner's token
+fn contents(token: &str) -> Vec<u8> {
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}\n{}\n", since_epoch.as_secs(), token).into_bytes()
+}
+
+/// Owner's token in the contents of a lock file
+fn owner(contents: &str) -> Option<&str> {
+    contents.lines().nth(1).filter(|token| !token.is_empty())
+}
+
+/// A token no other lock, in this instance or another, will have. Extensions can't see
+/// their process id, so it comes from the time and the randomly seeded `RandomState`.
+fn unique_token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", hasher.finish())
+}
+
+/// Remove a stale lock. Another instance may break the same lock and take a new one in
+/// the meantime, so the lock is first moved out of the way under a name of our own, and
+/// put back if it turns out to be fresh.
+fn break_stale(fs: &dyn FileSystem, path: &str, token: &str) {
+    let moved = format!("{}.stale-{}", path, token);
+    if fs.rename(path, &moved).is_err() {
+        // Someone else got to it first
+        return;
+    }
+    let is_stale = fs
+        .metadata(&moved)
+        .ok()
+        .and_then(|metadata| metadata.modified)
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE);
+    if !is_stale {
+        if let Ok(contents) = fs.read(&moved) {
+            fs.create_new(path, &contents).ok();
+        }
+    }
+    fs.remove_file(&moved).ok();
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        // A lock broken as stale may belong to another instance by now
+        if self.is_owned() {
+            self.fs.remove_file(&self.path).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+    use crate::progress::ToolProgress;
+
+    const VERSION_DIR: &str = "fake-tool-1.0.0";
+
+    fn age(fs: &MemoryFileSystem, path: &str) -> Duration {
+        let modified = fs.metadata(path).unwrap().modified.unwrap();
+        modified.elapsed().unwrap_or_default()
+    }
+
+    #[test]
+    fn gives_up_waiting_for_a_held_lock() {
+        let fs = MemoryFileSystem::new();
+        let held = InstallLock::acquire(Arc::new(fs.clone()), VERSION_DIR, "test").unwrap();
+
+        let error =
+            InstallLock::acquire_within(Arc::new(fs.clone()), VERSION_DIR, "test", Duration::ZERO)
+                .err()
+                .unwrap();
+        assert!(
+            matches!(error, InstallError::Locked { ref path, .. } if path == ".fake-tool-1.0.0.lock")
+        );
+
+        drop(held);
+        assert!(!fs.exists(".fake-tool-1.0.0.lock"));
+        InstallLock::acquire_within(Arc::new(fs), VERSION_DIR, "test", Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn breaks_stale_locks() {
+        let fs = MemoryFileSystem::new();
+        let path = InstallLock::path(VERSION_DIR);
+        fs.put(&path, b"0\n");
+        fs.backdate(&path, STALE_LOCK_AGE + Duration::from_secs(60));
+
+        let _lock =
+            InstallLock::acquire_within(Arc::new(fs.clone()), VERSION_DIR, "test", Duration::ZERO)
+                .unwrap();
+        assert!(age(&fs, &path) < STALE_LOCK_AGE);
+    }
+
+    #[test]
+    fn puts_back_a_lock_that_is_no_longer_stale() {
+        // Another instance broke the stale lock and took a new one in the meantime
+        let fs = MemoryFileSystem::new();
+        let path = InstallLock::path(VERSION_DIR);
+        fs.put(&path, b"0\nother\n");
+
+        break_stale(&fs, &path, "mine");
+        assert_eq!(fs.read(&path).unwrap(), b"0\nother\n");
+        assert_eq!(fs.read_dir("").unwrap(), vec![path]);
+    }
+
+    #[test]
+    fn leaves_alone_a_lock_it_no_longer_owns() {
+        let fs = MemoryFileSystem::new();
+        let lock = InstallLock::acquire(Arc::new(fs.clone()), VERSION_DIR, "test").unwrap();
+        let path = InstallLock::path(VERSION_DIR);
+
+        // Broken as stale and taken by another instance
+        fs.put(&path, b"0\nother\n");
+        if let Some(earlier) = Instant::now().checked_sub(REFRESH_INTERVAL) {
+            lock.refreshed.set(earlier);
+        }
+        lock.refresh();
+        drop(lock);
+        assert_eq!(fs.read(&path).unwrap(), b"0\nother\n");
+    }
+
+    #[test]
+    fn gives_each_lock_its_own_token() {
+        assert_ne!(unique_token(), unique_token());
+    }
+
+    #[test]
+    fn refreshes_the_lock_while_installing() {
+        let fs = MemoryFileSystem::new();
+        let lock = InstallLock::acquire(Arc::new(fs.clone()), VERSION_DIR, "test").unwrap();
+        let path = InstallLock::path(VERSION_DIR);
+        let progress = ToolProgress { tool: "fake-tool" };
+
+        // Within the refresh interval the lock file is left alone
+        fs.backdate(&path, Duration::from_secs(5 * 60));
+        lock.heartbeat(&progress).advance();
+        assert!(age(&fs, &path) >= Duration::from_secs(5 * 60));
+
+        // Once it has passed, headway in the install renews the lock
+        if let Some(earlier) = Instant::now().checked_sub(REFRESH_INTERVAL) {
+            lock.refreshed.set(earlier);
+        }
+        lock.heartbeat(&progress).advance();
+        assert!(age(&fs, &path) < REFRESH_INTERVAL);
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zed_extension_api::{self as zed, Result};
+
+use crate::checksum::{to_hex, DigestAlgorithm, ExpectedDigest};
+use crate::file_system::FileSystem;
+use crate::validation::ExtractedFile;
+
+/// Manifest file inside each version directory
+pub const MANIFEST_FILE: &str = ".install-manifest.json";
+
+/// Size and SHA-256 of one installed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// What was installed into a version directory, and from where
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallManifest {
+    pub version: String,
+    /// Download URL, or the path of a side-loaded archive
+    pub source: String,
+    /// Published digest the archive was verified against, as `<algorithm>:<hex>`
+    pub archive_digest: Option<String>,
+    /// Binary chosen at install time, relative to the version directory
+    #[serde(default)]
+    pub binary: Option<String>,
+    /// Seconds since the Unix epoch
+    pub installed_at: u64,
+    /// Installed files by path relative to the version directory
+    pub files: BTreeMap<String, ManifestFile>,
+}
+
+impl InstallManifest {
+    /// Hash the files of a fresh install in `dir`
+    pub fn create(
+        fs: &dyn FileSystem,
+        dir: &str,
+        version: &str,
+        source: &str,
+        archive_digest: Option<&ExpectedDigest>,
+        binary: &str,
+        extracted: &[ExtractedFile],
+    ) -> Result<Self> {
+        let files = extracted
+            .iter()
+            .map(|file| {
+                let sha256 = hash_file(fs, &format!("{}/{}", dir, file.path))
+                    .map_err(|e| format!("failed to hash {}: {}", file.path, e))?;
+                Ok((
+                    file.path.clone(),
+                    ManifestFile {
+                        size: file.size,
+                        sha256,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            version: version.to_string(),
+            source: source.to_string(),
+            archive_digest: archive_digest
+                .map(|digest| format!("{}:{}", digest.algorithm.name(), to_hex(&digest.value))),
+            binary: Some(binary.to_string()),
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            files,
+        })
+    }
+
+    /// Manifest of an install made before manifests existed, trusting its current contents
+    pub fn adopt(fs: &dyn FileSystem, dir: &str, version: &str, binary: &str) -> Result<Self> {
+        let mut extracted = Vec::new();
+        list_files(fs, dir, "", &mut extracted)
+            .map_err(|e| format!("failed to list {}: {}", dir, e))?;
+        Self::create(fs, dir, version, "unknown", None, binary, &extracted)
+    }
+
+    /// The manifest of `dir`; None when missing or unreadable
+    pub fn load(fs: &dyn FileSystem, dir: &str) -> Option<Self> {
+        fs.read_to_string(&format!("{}/{}", dir, MANIFEST_FILE))
+            .ok()
+            .and_then(|contents| zed::serde_json::from_str(&contents).ok())
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem, dir: &str) -> Result<()> {
+        let contents = zed::serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize install manifest: {}", e))?;
+        let path = format!("{}/{}", dir, MANIFEST_FILE);
+        fs.write(&path, contents.as_bytes())
+            .map_err(|e| format!("failed to write {}: {}", path, e))
+    }
+
+    /// Files in `dir` that no longer match the manifest. Without `hashes`, only presence
+    /// and sizes are compared, which is cheap enough to do on every reuse.
+    pub fn verify(&self, fs: &dyn FileSystem, dir: &str, hashes: bool) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (path, expected) in &self.files {
+            let full_path = format!("{}/{}", dir, path);
+            match fs.metadata(&full_path) {
+                Ok(stat) if !stat.is_file() => problems.push(format!("{} is missing", path)),
+                Ok(stat) if stat.len != expected.size => problems.push(format!(
+                    "{} is {} bytes, expected {}",
+                    path, stat.len, expected.size
+                )),
+                Ok(_) if hashes => match hash_file(fs, &full_path) {
+                    Ok(sha256) if sha256 == expected.sha256 => {}
+                    Ok(_) => problems.push(format!("{} was modified", path)),
+                    Err(e) => problems.push(format!("{} is unreadable: {}", path, e)),
+                },
+                Ok(_) => {}
+                Err(_) => problems.push(format!("{} is missing", path)),
+            }
+        }
+        problems
+    }
+}
+
+fn hash_file(fs: &dyn FileSystem, path: &str) -> std::io::Result<String> {
+    DigestAlgorithm::Sha256
+        .compute_reader(fs.open(path)?)
+        .map(|hash| to_hex(&hash))
+}
+
+/// Regular files under `dir`, relative to the version directory
+fn list_files(
+    fs: &dyn FileSystem,
+    root: &str,
+    dir: &str,
+    files: &mut Vec<ExtractedFile>,
+) -> std::io::Result<()> {
+    let full_dir = match dir {
+        "" => root.to_string(),
+        dir => format!("{}/{}", root, dir),
+    };
+    for name in fs.read_dir(&full_dir)? {
+        let path = match dir {
+            "" => name.clone(),
+            dir => format!("{}/{}", dir, name),
+        };
+        let stat = fs.metadata(&format!("{}/{}", full_dir, name))?;
+        if stat.is_dir {
+            list_files(fs, root, &path, files)?;
+        } else if path != MANIFEST_FILE {
+            files.push(ExtractedFile {
+                path,
+                size: stat.len,
+                executable: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn detects_deleted_and_modified_files() {
+        let fs = MemoryFileSystem::new();
+        fs.put("tool-1.0.0/tools/server.dll", b"assembly");
+        fs.put("tool-1.0.0/server.json", b"{}");
+        let dir = "tool-1.0.0";
+
+        let manifest = InstallManifest::adopt(&fs, dir, "1.0.0", "tools/server.dll").unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(manifest.verify(&fs, dir, true).is_empty());
+
+        fs.put("tool-1.0.0/tools/server.dll", b"tampered");
+        assert!(manifest.verify(&fs, dir, false).is_empty());
+        assert_eq!(
+            manifest.verify(&fs, dir, true),
+            vec!["tools/server.dll was modified"]
+        );
+
+        fs.remove_file("tool-1.0.0/server.json").unwrap();
+        assert_eq!(
+            manifest.verify(&fs, dir, false),
+            vec!["server.json is missing"]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zed_extension_api::{self as zed, Result};
+
+use crate::file_system::FileSystem;
+
+/// State file in the extension work directory
+const LAUNCH_HISTORY_FILE: &str = ".launch-history.json";
+
+/// Launches of a version that never confirmed startup before it counts as broken
+pub const MAX_UNCONFIRMED_LAUNCHES: u32 = 2;
+
+/// Launch outcomes of one tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolLaunches {
+    /// Last version that started successfully
+    pub known_good: Option<String>,
+    /// Launches per version since it last started successfully
+    pub unconfirmed: BTreeMap<String, u32>,
+}
+
+/// Which installed versions started successfully, persisted across Zed processes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchHistory {
+    pub tools: BTreeMap<String, ToolLaunches>,
+}
+
+impl LaunchHistory {
+    /// Load the history file; a missing or unreadable file is an empty history
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        fs.read_to_string(LAUNCH_HISTORY_FILE)
+            .ok()
+            .and_then(|contents| zed::serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem) -> Result<()> {
+        let contents = zed::serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize launch history: {}", e))?;
+        // Write then rename, so a concurrent reader never sees a partial file
+        let temp_file = format!("{}.tmp", LAUNCH_HISTORY_FILE);
+        fs.write(&temp_file, contents.as_bytes())
+            .and_then(|()| fs.rename(&temp_file, LAUNCH_HISTORY_FILE))
+            .map_err(|e| format!("failed to write {}: {}", LAUNCH_HISTORY_FILE, e))
+    }
+
+    pub fn known_good(&self, tool: &str) -> Option<&str> {
+        self.tools.get(tool)?.known_good.as_deref()
+    }
+
+    /// The known-good version to fall back to, when `version` keeps failing to start
+    pub fn rollback_target(&self, tool: &str, version: &str) -> Option<&str> {
+        let launches = self.tools.get(tool)?;
+        let known_good = launches.known_good.as_deref()?;
+        let failures = launches
+            .unconfirmed
+            .get(version)
+            .copied()
+            .unwrap_or_default();
+        (known_good != version && failures >= MAX_UNCONFIRMED_LAUNCHES).then_some(known_good)
+    }
+
+    /// Count a launch of `version`; it stays unconfirmed until `record_success`
+    pub fn record_launch(&mut self, tool: &str, version: &str) {
+        *self
+            .tools
+            .entry(tool.to_string())
+            .or_default()
+            .unconfirmed
+            .entry(version.to_string())
+            .or_default() += 1;
+    }
+
+    /// Mark `version` as known-good. Returns whether anything changed.
+    pub fn record_success(&mut self, tool: &str, version: &str) -> bool {
+        let launches = self.tools.entry(tool.to_string()).or_default();
+        let changed = launches.known_good.as_deref() != Some(version)
+            || launches.unconfirmed.contains_key(version);
+        launches.known_good = Some(version.to_string());
+        launches.unconfirmed.remove(version);
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_back_after_repeated_unconfirmed_launches() {
+        let mut history = LaunchHistory::default();
+        history.record_launch("csharp-language-server", "0.15.0");
+        assert!(history.record_success("csharp-language-server", "0.15.0"));
+        assert!(!history.record_success("csharp-language-server", "0.15.0"));
+
+        history.record_launch("csharp-language-server", "0.16.0");
+        assert_eq!(
+            history.rollback_target("csharp-language-server", "0.16.0"),
+            None
+        );
+        history.record_launch("csharp-language-server", "0.16.0");
+        assert_eq!(
+            history.rollback_target("csharp-language-server", "0.16.0"),
+            Some("0.15.0")
+        );
+
+        // A newer release gets its own chance
+        assert_eq!(
+            history.rollback_target("csharp-language-server", "0.17.0"),
+            None
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zed_extension_api::{self as zed, Result};
+
+use crate::file_system::FileSystem;
+use crate::install_lock;
+use crate::version::Version;
+
+/// State file in the extension work directory
+const INSTALL_USAGE_FILE: &str = ".install-usage.json";
+
+/// When each version directory was last resolved for use, persisted across Zed processes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallUsage {
+    /// Seconds since the Unix epoch, by version directory name
+    pub dirs: BTreeMap<String, u64>,
+}
+
+impl InstallUsage {
+    /// Load the usage file; a missing or unreadable file is an empty record
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        fs.read_to_string(INSTALL_USAGE_FILE)
+            .ok()
+            .and_then(|contents| zed::serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem) -> Result<()> {
+        let contents = zed::serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize install usage: {}", e))?;
+        // Write then rename, so a concurrent reader never sees a partial file
+        let temp_file = format!("{}.tmp", INSTALL_USAGE_FILE);
+        fs.write(&temp_file, contents.as_bytes())
+            .and_then(|()| fs.rename(&temp_file, INSTALL_USAGE_FILE))
+            .map_err(|e| format!("failed to write {}: {}", INSTALL_USAGE_FILE, e))
+    }
+
+    pub fn touch(&mut self, version_dir: &str) {
+        self.dirs.insert(version_dir.to_string(), now());
+    }
+}
+
+/// What an entry of the work directory is to the tool it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// An installed version directory, `<prefix>-<version>`
+    Install(Version),
+    /// A directory an install is extracted into before it's renamed into place
+    Staging,
+    /// A download kept so the next attempt can resume it
+    PartialDownload,
+    /// The install lock of a version directory
+    Lock,
+}
+
+/// One entry of the work directory belonging to a tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkDirEntry {
+    /// File or directory name in the work directory
+    pub name: String,
+    /// Version directory the entry belongs to; the entry itself for installs
+    pub version_dir: String,
+    pub kind: EntryKind,
+    /// Bytes on disk, including everything below a directory
+    pub size: u64,
+    /// Time since the entry was last modified
+    pub age: Option<Duration>,
+    /// Seconds since the Unix epoch when the install was last used, or installed
+    pub last_used: Option<u64>,
+}
+
+impl WorkDirEntry {
+    /// Classify a work directory entry for the tool with the given prefix; None when it
+    /// isn't the tool's. Only names the extension creates are the tool's: version
+    /// directories `<prefix>-<version>`, and their `.<prefix>-<version>.staging` directory,
+    /// `.staging.partial` download and `.lock` file. Anything else, such as a side-loaded
+    /// archive or a mirror folder named after the tool, is left alone.
+    pub fn classify(prefix: &str, name: &str, is_dir: bool) -> Option<(String, EntryKind)> {
+        let version_of = |version_dir: &str| {
+            Version::parse(version_dir.strip_prefix(prefix)?.strip_prefix('-')?).ok()
+        };
+        let Some(hidden) = name.strip_prefix('.') else {
+            let version = version_of(name).filter(|_| is_dir)?;
+            return Some((name.to_string(), EntryKind::Install(version)));
+        };
+
+        let suffixes = [
+            (".staging.partial", false, EntryKind::PartialDownload),
+            (".staging", true, EntryKind::Staging),
+            (".lock", false, EntryKind::Lock),
+        ];
+        suffixes
+            .into_iter()
+            .find_map(|(suffix, dir, kind)| {
+                let version_dir = hidden.strip_suffix(suffix)?;
+                Some((version_dir, dir, kind))
+            })
+            .filter(|(version_dir, dir, _)| *dir == is_dir && version_of(version_dir).is_some())
+            .map(|(version_dir, _, kind)| (version_dir.to_string(), kind))
+    }
+}
+
+/// How much of a tool's work directory to keep
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Installed versions to keep besides the protected ones, newest first; None keeps
+    /// every version, e.g. while a version is pinned and other worktrees may pin others
+    pub keep_versions: Option<usize>,
+    /// Kept versions unused for longer than this are removed anyway
+    pub max_unused: Duration,
+    /// Partial downloads older than this aren't worth resuming
+    pub max_partial_age: Duration,
+}
+
+impl RetentionPolicy {
+    pub fn new(keep_versions: Option<usize>) -> Self {
+        Self {
+            keep_versions,
+            max_unused: Duration::from_secs(90 * 24 * 60 * 60),
+            max_partial_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    /// Entries to remove. Nothing belonging to a version directory whose install lock is
+    /// held is touched, nor anything `protected` names: version directories, whose
+    /// leftovers are kept too, or single entries such as a folder a source reads from.
+    pub fn removals<'a>(
+        &self,
+        entries: &'a [WorkDirEntry],
+        protected: &[&str],
+        now: u64,
+    ) -> Vec<&'a WorkDirEntry> {
+        let locked: HashSet<&str> = entries
+            .iter()
+            .filter(|entry| {
+                entry.kind == EntryKind::Lock
+                    && entry
+                        .age
+                        .is_none_or(|age| age <= install_lock::STALE_LOCK_AGE)
+            })
+            .map(|entry| entry.version_dir.as_str())
+            .collect();
+        let in_use = |entry: &WorkDirEntry| {
+            locked.contains(entry.version_dir.as_str())
+                || protected.contains(&entry.version_dir.as_str())
+                || protected.contains(&entry.name.as_str())
+        };
+
+        let mut removals: Vec<&WorkDirEntry> = entries
+            .iter()
+            .filter(|entry| match entry.kind {
+                EntryKind::Lock => {
+                    !locked.contains(entry.version_dir.as_str())
+                        && !protected.contains(&entry.name.as_str())
+                }
+                EntryKind::Staging => !in_use(entry),
+                EntryKind::PartialDownload => {
+                    !in_use(entry) && entry.age.is_some_and(|age| age > self.max_partial_age)
+                }
+                EntryKind::Install(_) => false,
+            })
+            .collect();
+
+        if let Some(keep_versions) = self.keep_versions {
+            let mut installs: Vec<&WorkDirEntry> = entries
+                .iter()
+                .filter(|entry| matches!(entry.kind, EntryKind::Install(_)) && !in_use(entry))
+                .collect();
+            installs.sort_by(|a, b| match (&b.kind, &a.kind) {
+                (EntryKind::Install(b), EntryKind::Install(a)) => b.cmp(a),
+                _ => std::cmp::Ordering::Equal,
+            });
+            let max_unused = self.max_unused.as_secs();
+            removals.extend(
+                installs
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, entry)| {
+                        *i >= keep_versions
+                            || entry
+                                .last_used
+                                .is_some_and(|last_used| now.saturating_sub(last_used) > max_unused)
+                    })
+                    .map(|(_, entry)| entry),
+            );
+        }
+        removals
+    }
+}
+
+/// What a garbage collection run found and did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Entries that were removed
+    pub removed: Vec<String>,
+    /// Bytes freed by removing them
+    pub freed: u64,
+    /// Bytes the tool still takes up
+    pub retained: u64,
+}
+
+/// Bytes taken up by a file, or a directory and everything in it
+pub fn disk_usage(fs: &dyn FileSystem, path: &str) -> u64 {
+    match fs.metadata(path) {
+        Ok(stat) if stat.is_dir => fs
+            .read_dir(path)
+            .into_iter()
+            .flatten()
+            .map(|name| disk_usage(fs, &format!("{}/{}", path, name)))
+            .sum(),
+        Ok(stat) => stat.len,
+        Err(_) => 0,
+    }
+}
+
+/// Render a byte count for logs, e.g. `12.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Render a tool's work directory entries for people: each with its size and, for
+/// installs, when it was last used, under the tool's total
+pub fn describe_usage(prefix: &str, entries: &[WorkDirEntry], now: u64) -> String {
+    let total = entries.iter().map(|entry| entry.size).sum();
+    let mut text = format!("{}: {}\n", prefix, format_size(total));
+    if entries.is_empty() {
+        text.push_str("  not installed\n");
+    }
+    for entry in entries {
+        let detail = match entry.kind {
+            EntryKind::Install(_) => match entry.last_used {
+                Some(last_used) => match now.saturating_sub(last_used) / (24 * 60 * 60) {
+                    0 => "last used today".to_string(),
+                    1 => "last used yesterday".to_string(),
+                    days => format!("last used {} days ago", days),
+                },
+                None => "never used".to_string(),
+            },
+            EntryKind::Staging => "interrupted install".to_string(),
+            EntryKind::PartialDownload => "partial download".to_string(),
+            EntryKind::Lock => "install lock".to_string(),
+        };
+        text.push_str(&format!(
+            "  {}: {}, {}\n",
+            entry.name,
+            format_size(entry.size),
+            detail
+        ));
+    }
+    text
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn entry(name: &str, is_dir: bool, age_days: u64, last_used: Option<u64>) -> WorkDirEntry {
+        let (version_dir, kind) = WorkDirEntry::classify("netcoredbg", name, is_dir).unwrap();
+        WorkDirEntry {
+            name: name.to_string(),
+            version_dir,
+            kind,
+            size: 1,
+            age: Some(Duration::from_secs(age_days * DAY)),
+            last_used,
+        }
+    }
+
+    #[test]
+    fn classifies_work_dir_entries() {
+        let classify = |name, is_dir| WorkDirEntry::classify("netcoredbg", name, is_dir);
+        assert_eq!(
+            classify(".netcoredbg-3.1.2.staging.partial", false),
+            Some(("netcoredbg-3.1.2".to_string(), EntryKind::PartialDownload))
+        );
+        assert_eq!(
+            classify(".netcoredbg-3.1.2.lock", false),
+            Some(("netcoredbg-3.1.2".to_string(), EntryKind::Lock))
+        );
+        assert_eq!(
+            classify(".netcoredbg-3.1.2.staging", true),
+            Some(("netcoredbg-3.1.2".to_string(), EntryKind::Staging))
+        );
+        assert_eq!(
+            classify("netcoredbg-3.1.2", true),
+            Some((
+                "netcoredbg-3.1.2".to_string(),
+                EntryKind::Install(Version::parse("3.1.2").unwrap())
+            ))
+        );
+
+        // Only names the extension creates, never what users put next to them
+        assert_eq!(classify("netcoredbg_old", true), None);
+        assert_eq!(classify("netcoredbg-mirror", true), None);
+        assert_eq!(classify(".netcoredbg-mirror.lock", false), None);
+        assert_eq!(classify("netcoredbg-3.1.2", false), None);
+        assert_eq!(classify(".netcoredbg-3.1.2.staging", false), None);
+        assert_eq!(classify("netcoredbg-3.1.2.tar.gz", false), None);
+        assert_eq!(classify(".update-state.json", false), None);
+    }
+
+    #[test]
+    fn removes_leftovers_and_stale_versions() {
+        let now = 1000 * DAY;
+        let entries = [
+            entry("netcoredbg-3.1.2", true, 1, Some(now)),
+            entry("netcoredbg-3.1.1", true, 30, Some(now - 20 * DAY)),
+            entry("netcoredbg-3.1.0", true, 60, Some(now - 40 * DAY)),
+            entry("netcoredbg-3.0.0", true, 400, Some(now - 200 * DAY)),
+            entry("netcoredbg-2.0.0", true, 500, Some(now - 300 * DAY)),
+            entry(".netcoredbg-3.2.0.staging", true, 0, None),
+            entry(".netcoredbg-3.2.0.lock", false, 0, None),
+            entry(".netcoredbg-3.1.9.staging.partial", false, 1, None),
+            entry(".netcoredbg-3.1.8.staging.partial", false, 30, None),
+        ];
+
+        let policy = RetentionPolicy::new(Some(2));
+        let removed: Vec<&str> = policy
+            .removals(&entries, &["netcoredbg-3.1.2", "netcoredbg-2.0.0"], now)
+            .into_iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(
+            removed,
+            vec![".netcoredbg-3.1.8.staging.partial", "netcoredbg-3.0.0"]
+        );
+
+        // Entries can be protected by name too
+        let removed = policy.removals(
+            &entries,
+            &[
+                "netcoredbg-3.1.2",
+                "netcoredbg-2.0.0",
+                ".netcoredbg-3.1.8.staging.partial",
+            ],
+            now,
+        );
+        assert_eq!(removed.len(), 1);
+
+        // Without version pruning only leftovers go
+        assert_eq!(
+            RetentionPolicy::new(None)
+                .removals(&entries, &[], now)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn describes_disk_usage() {
+        let now = 1000 * DAY;
+        let entries = [
+            entry("netcoredbg-3.1.2", true, 1, Some(now)),
+            entry("netcoredbg-3.1.1", true, 30, Some(now - 20 * DAY)),
+            entry(".netcoredbg-3.2.0.staging.partial", false, 0, None),
+        ];
+        assert_eq!(
+            describe_usage("netcoredbg", &entries, now),
+            "netcoredbg: 3 B\n  netcoredbg-3.1.2: 1 B, last used today\n  netcoredbg-3.1.1: 1 B, last used 20 days ago\n  .netcoredbg-3.2.0.staging.partial: 1 B, partial download\n"
+        );
+        assert_eq!(
+            describe_usage("netcoredbg", &[], now),
+            "netcoredbg: 0 B\n  not installed\n"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use zed_extension_api::{self as zed, Result};
+
+use crate::checksum::{DigestAlgorithm, ExpectedDigest};
+use crate::dotnet::TargetFramework;
+use crate::http::{self, HttpClient};
+use crate::version_config::{auth_headers, HttpAuth};
+
+/// nuget.org's package base address (the v3 "flat container" resource)
+pub const NUGET_ORG_FLAT_CONTAINER: &str = "https://api.nuget.org/v3-flatcontainer";
+/// nuget.org's registration hive with uncompressed, SemVer 1 metadata
+const NUGET_ORG_REGISTRATIONS: &str = "https://api.nuget.org/v3/registration5-semver1";
+
+/// Metadata read from a package's `.nuspec`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Nuspec {
+    pub id: String,
+    pub version: String,
+    /// Frameworks the package declares dependency or framework-reference groups for
+    pub target_frameworks: Vec<TargetFramework>,
+}
+
+impl Nuspec {
+    pub fn parse(xml: &str) -> Result<Self> {
+        let document =
+            roxmltree::Document::parse(xml).map_err(|e| format!("invalid nuspec: {}", e))?;
+
+        // The nuspec namespace changes between schema versions, so match on local names only
+        let metadata = document
+            .root_element()
+            .children()
+            .find(|node| node.tag_name().name() == "metadata")
+            .ok_or_else(|| "nuspec has no <metadata> element".to_string())?;
+
+        let child_text = |name: &str| {
+            metadata
+                .children()
+                .find(|node| node.tag_name().name() == name)
+                .and_then(|node| node.text())
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut target_frameworks: Vec<TargetFramework> = metadata
+            .descendants()
+            .filter(|node| node.tag_name().name() == "group")
+            .filter_map(|node| node.attribute("targetFramework"))
+            .filter_map(TargetFramework::parse)
+            .collect();
+        target_frameworks.sort();
+        target_frameworks.dedup();
+
+        Ok(Self {
+            id: child_text("id"),
+            version: child_text("version"),
+            target_frameworks,
+        })
+    }
+}
+
+/// Resources advertised by a NuGet v3 service index (`.../index.json`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceIndex {
+    /// `PackageBaseAddress/3.0.0`, the flat container
+    pub package_base_address: Option<String>,
+    /// `RegistrationsBaseUrl`, where package metadata (including hashes) lives
+    pub registrations_base_url: Option<String>,
+}
+
+impl ServiceIndex {
+    pub fn parse(json: &str) -> Result<Self> {
+        let index: zed::serde_json::Value =
+            zed::serde_json::from_str(json).map_err(|e| format!("invalid service index: {}", e))?;
+        let resources = index
+            .get("resources")
+            .and_then(|resources| resources.as_array())
+            .ok_or_else(|| "service index has no resources array".to_string())?;
+
+        let resource = |matches: &dyn Fn(&str) -> bool| {
+            resources.iter().find_map(|resource| {
+                let kind = resource.get("@type")?.as_str()?;
+                let id = resource.get("@id")?.as_str()?;
+                matches(kind).then(|| id.to_string())
+            })
+        };
+
+        Ok(Self {
+            package_base_address: resource(&|kind| kind == "PackageBaseAddress/3.0.0"),
+            // Prefer the unversioned (uncompressed) hive, like nuget.org's registration5-semver1
+            registrations_base_url: resource(&|kind| kind == "RegistrationsBaseUrl")
+                .or_else(|| resource(&|kind| kind.starts_with("RegistrationsBaseUrl/"))),
+        })
+    }
+}
+
+/// Client for a NuGet v3 flat container (package base address)
+pub struct FlatContainer<'a> {
+    http: &'a dyn HttpClient,
+    base_url: String,
+    package_id: String,
+    registrations_base_url: Option<String>,
+    auth: Option<HttpAuth>,
+}
+
+impl<'a> FlatContainer<'a> {
+    pub fn new(http: &'a dyn HttpClient, base_url: &str, package_id: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        Self {
+            http,
+            base_url: base_url.to_string(),
+            // Flat container paths are always lowercase
+            package_id: package_id.to_lowercase(),
+            registrations_base_url: (base_url == NUGET_ORG_FLAT_CONTAINER)
+                .then(|| NUGET_ORG_REGISTRATIONS.to_string()),
+            auth: None,
+        }
+    }
+
+    /// Open a feed given either its service index (`.../index.json`) or its flat container URL
+    pub fn open(
+        http: &'a dyn HttpClient,
+        feed_url: &str,
+        package_id: &str,
+        auth: Option<&HttpAuth>,
+    ) -> Result<Self> {
+        if !feed_url.ends_with("/index.json") {
+            let mut feed = Self::new(http, feed_url, package_id);
+            feed.auth = auth.cloned();
+            return Ok(feed);
+        }
+
+        let json = String::from_utf8(fetch(http, feed_url, auth)?)
+            .map_err(|e| format!("invalid UTF-8 in {}: {}", feed_url, e))?;
+        let index = ServiceIndex::parse(&json).map_err(|e| format!("{}: {}", feed_url, e))?;
+        let base_url = index.package_base_address.ok_or_else(|| {
+            format!(
+                "{} doesn't advertise a PackageBaseAddress/3.0.0 resource",
+                feed_url
+            )
+        })?;
+
+        let mut feed = Self::new(http, &base_url, package_id);
+        feed.registrations_base_url = index
+            .registrations_base_url
+            .map(|url| url.trim_end_matches('/').to_string());
+        feed.auth = auth.cloned();
+        Ok(feed)
+    }
+
+    /// All published versions of the package, in feed order (ascending)
+    pub fn list_versions(&self) -> Result<Vec<String>> {
+        let url = format!("{}/{}/index.json", self.base_url, self.package_id);
+        let index: zed::serde_json::Value =
+            zed::serde_json::from_slice(&fetch(self.http, &url, self.auth.as_ref())?)
+                .map_err(|e| format!("invalid version index {}: {}", url, e))?;
+
+        index
+            .get("versions")
+            .and_then(|versions| versions.as_array())
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .ok_or_else(|| format!("version index {} has no versions array", url))
+    }
+
+    /// The `.nuspec` of a specific version
+    pub fn nuspec(&self, version: &str) -> Result<Nuspec> {
+        let url = format!(
+            "{}/{}/{}/{}.nuspec",
+            self.base_url,
+            self.package_id,
+            version.to_lowercase(),
+            self.package_id
+        );
+        let xml = String::from_utf8(fetch(self.http, &url, self.auth.as_ref())?)
+            .map_err(|e| format!("invalid UTF-8 in {}: {}", url, e))?;
+        Nuspec::parse(&xml).map_err(|e| format!("{}: {}", url, e))
+    }
+
+    /// Download URL of the `.nupkg` for a specific version
+    pub fn package_url(&self, version: &str) -> String {
+        let version = version.to_lowercase();
+        format!(
+            "{}/{}/{}/{}.{}.nupkg",
+            self.base_url, self.package_id, version, self.package_id, version
+        )
+    }
+
+    /// The package hash published in the feed's registration metadata, or None when the
+    /// feed has no registration resource (flat container URLs other than nuget.org's)
+    pub fn package_digest(&self, version: &str) -> Result<Option<ExpectedDigest>> {
+        let Some(registrations_base_url) = &self.registrations_base_url else {
+            return Ok(None);
+        };
+
+        // The registration leaf points at (or embeds) the catalog entry, which carries the hash
+        let leaf_url = format!(
+            "{}/{}/{}.json",
+            registrations_base_url,
+            self.package_id,
+            version.to_lowercase()
+        );
+        let leaf = self.fetch_json(&leaf_url)?;
+        let (entry, entry_source) = match leaf.get("catalogEntry") {
+            Some(zed::serde_json::Value::String(catalog_url)) => {
+                (self.fetch_json(catalog_url)?, catalog_url.clone())
+            }
+            Some(entry @ zed::serde_json::Value::Object(_)) => (entry.clone(), leaf_url.clone()),
+            _ => {
+                return Err(format!(
+                    "registration leaf {} has no catalogEntry",
+                    leaf_url
+                ))
+            }
+        };
+
+        let Some(hash) = entry.get("packageHash").and_then(|v| v.as_str()) else {
+            return Ok(None);
+        };
+        let algorithm = match entry.get("packageHashAlgorithm").and_then(|v| v.as_str()) {
+            Some("SHA512") | None => DigestAlgorithm::Sha512,
+            Some("SHA256") => DigestAlgorithm::Sha256,
+            Some(other) => {
+                return Err(format!(
+                    "unsupported NuGet package hash algorithm: {}",
+                    other
+                ))
+            }
+        };
+
+        let digest = ExpectedDigest::from_base64(algorithm, hash, &entry_source)?;
+        Ok(Some(
+            match entry.get("packageSize").and_then(|v| v.as_u64()) {
+                Some(size) => digest.with_size(size),
+                None => digest,
+            },
+        ))
+    }
+
+    fn fetch_json(&self, url: &str) -> Result<zed::serde_json::Value> {
+        zed::serde_json::from_slice(&fetch(self.http, url, self.auth.as_ref())?)
+            .map_err(|e| format!("invalid JSON from {}: {}", url, e))
+    }
+}
+
+fn fetch(http: &dyn HttpClient, url: &str, auth: Option<&HttpAuth>) -> Result<Vec<u8>> {
+    http.fetch(&http::get_request(url, auth_headers(auth)))
+        .map(|response| response.body)
+        .map_err(|e| format!("failed to fetch {}: {}", url, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::FakeHttpClient;
+
+    #[test]
+    fn parses_nuspec_framework_groups() {
+        let nuspec = Nuspec::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <package xmlns="http://schemas.microsoft.com/packaging/2013/05/nuspec.xsd">
+              <metadata>
+                <id>csharp-ls</id>
+                <version>0.16.0</version>
+                <packageTypes><packageType name="DotnetTool" /></packageTypes>
+                <dependencies>
+                  <group targetFramework="net9.0" />
+                  <group targetFramework=".NETCoreApp8.0" />
+                  <group targetFramework=".NETStandard2.0" />
+                </dependencies>
+              </metadata>
+            </package>"#,
+        )
+        .unwrap();
+
+        assert_eq!(nuspec.id, "csharp-ls");
+        assert_eq!(nuspec.version, "0.16.0");
+        assert_eq!(
+            nuspec.target_frameworks,
+            vec![
+                TargetFramework { major: 8, minor: 0 },
+                TargetFramework { major: 9, minor: 0 }
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_lowercase_flat_container_urls() {
+        let http = FakeHttpClient::new();
+        let feed = FlatContainer::new(&http, "https://feed.example/v3/", "CSharp-LS");
+        assert_eq!(
+            feed.package_url("1.0.0-Beta"),
+            "https://feed.example/v3/csharp-ls/1.0.0-beta/csharp-ls.1.0.0-beta.nupkg"
+        );
+    }
+
+    #[test]
+    fn finds_service_index_resources() {
+        let index = ServiceIndex::parse(
+            r#"{
+              "version": "3.0.0",
+              "resources": [
+                { "@id": "https://feed.example/v3/registration-gz/", "@type": "RegistrationsBaseUrl/3.4.0" },
+                { "@id": "https://feed.example/v3/flat/", "@type": "PackageBaseAddress/3.0.0" },
+                { "@id": "https://feed.example/v3/registration/", "@type": "RegistrationsBaseUrl" }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            index.package_base_address.as_deref(),
+            Some("https://feed.example/v3/flat/")
+        );
+        assert_eq!(
+            index.registrations_base_url.as_deref(),
+            Some("https://feed.example/v3/registration/")
+        );
+    }
+}
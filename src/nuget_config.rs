@@ -0,0 +1,439 @@
+use zed_extension_api::{self as zed, Result};
+
+use crate::logger;
+use crate::version_config::{HttpAuth, NugetFeed, VersionDirConfig, VersionSource};
+
+/// File names NuGet looks for in a directory
+const CONFIG_FILE_NAMES: [&str; 3] = ["nuget.config", "NuGet.config", "NuGet.Config"];
+/// The source the dotnet CLI falls back to without a user-level NuGet.Config
+const NUGET_ORG_SOURCE: (&str, &str) = ("nuget.org", "https://api.nuget.org/v3/index.json");
+
+/// An enabled package source after merging, with its credentials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageSource {
+    pub name: String,
+    pub url: String,
+    pub protocol_version: Option<String>,
+    pub credentials: Option<Credentials>,
+}
+
+/// `packageSourceCredentials` of one source
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Option<String>,
+    /// Set when only an encrypted (Windows DPAPI) `Password` is configured
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct SourceEntry {
+    name: String,
+    url: String,
+    protocol_version: Option<String>,
+    /// Index of the config file that last defined the source; higher wins
+    priority: usize,
+}
+
+/// The merged result of `nuget.config` files applied in turn
+#[derive(Debug, Clone, Default)]
+pub struct NugetConfig {
+    sources: Vec<SourceEntry>,
+    disabled: Vec<String>,
+    credentials: Vec<(String, Credentials)>,
+    files_applied: usize,
+}
+
+impl NugetConfig {
+    /// What the dotnet CLI starts from without a user-level config
+    pub fn with_defaults() -> Self {
+        Self {
+            sources: vec![SourceEntry {
+                name: NUGET_ORG_SOURCE.0.to_string(),
+                url: NUGET_ORG_SOURCE.1.to_string(),
+                protocol_version: Some("3".to_string()),
+                priority: 0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// The worktree root's config file, applied on top of the dotnet CLI defaults. Returns
+    /// None when the worktree has no config file.
+    ///
+    /// The dotnet CLI also merges the user-level `NuGet.Config` and the files in the
+    /// directories above the worktree, but extensions can only read files inside the
+    /// worktree, so those aren't supported.
+    pub fn discover(worktree: &zed::Worktree, env: &[(String, String)]) -> Result<Option<Self>> {
+        Self::from_worktree(
+            |name| worktree.read_text_file(name).ok(),
+            &worktree.root_path(),
+            env,
+        )
+    }
+
+    /// `discover`, reading files of the worktree at `root_path` with `read_file`
+    fn from_worktree(
+        read_file: impl Fn(&str) -> Option<String>,
+        root_path: &str,
+        env: &[(String, String)],
+    ) -> Result<Option<Self>> {
+        let Some((name, xml)) = CONFIG_FILE_NAMES
+            .iter()
+            .find_map(|name| Some((name, read_file(name)?)))
+        else {
+            return Ok(None);
+        };
+        logger::Logger::debug(&format!(
+            "NugetConfig::discover: using {}/{}",
+            root_path, name
+        ));
+        let mut config = Self::with_defaults();
+        config
+            .apply(&xml, env)
+            .map_err(|e| format!("{}/{}: {}", root_path, name, e))?;
+        Ok(Some(config))
+    }
+
+    /// Apply one config file on top of the lower-priority files applied so far
+    pub fn apply(&mut self, xml: &str, env: &[(String, String)]) -> Result<()> {
+        let document =
+            roxmltree::Document::parse(xml).map_err(|e| format!("invalid nuget.config: {}", e))?;
+        self.files_applied += 1;
+        let priority = self.files_applied;
+
+        let section = |name: &'static str| {
+            document
+                .root_element()
+                .children()
+                .filter(move |node| node.tag_name().name().eq_ignore_ascii_case(name))
+        };
+        let attribute = |node: roxmltree::Node, name: &str| {
+            node.attribute(name).map(|value| expand_env(value, env))
+        };
+
+        for node in section("packageSources").flat_map(|section| section.children()) {
+            match node.tag_name().name() {
+                "clear" => self.sources.clear(),
+                "add" => {
+                    let (Some(name), Some(url)) =
+                        (attribute(node, "key"), attribute(node, "value"))
+                    else {
+                        continue;
+                    };
+                    self.sources
+                        .retain(|source| !source.name.eq_ignore_ascii_case(&name));
+                    self.sources.push(SourceEntry {
+                        name,
+                        url,
+                        protocol_version: attribute(node, "protocolVersion"),
+                        priority,
+                    });
+                }
+                "remove" => {
+                    if let Some(name) = attribute(node, "key") {
+                        self.sources
+                            .retain(|source| !source.name.eq_ignore_ascii_case(&name));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for node in section("disabledPackageSources").flat_map(|section| section.children()) {
+            match node.tag_name().name() {
+                "clear" => self.disabled.clear(),
+                "add" => {
+                    let Some(name) = attribute(node, "key") else {
+                        continue;
+                    };
+                    self.disabled
+                        .retain(|disabled| !disabled.eq_ignore_ascii_case(&name));
+                    if attribute(node, "value")
+                        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+                    {
+                        self.disabled.push(name);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for source in section("packageSourceCredentials")
+            .flat_map(|section| section.children())
+            .filter(|node| node.is_element())
+        {
+            let name = decode_element_name(source.tag_name().name());
+            let mut credentials = Credentials::default();
+            for item in source
+                .children()
+                .filter(|node| node.tag_name().name() == "add")
+            {
+                let (Some(key), Some(value)) = (attribute(item, "key"), attribute(item, "value"))
+                else {
+                    continue;
+                };
+                match key.to_ascii_lowercase().as_str() {
+                    "username" => credentials.username = value,
+                    "cleartextpassword" => credentials.password = Some(value),
+                    "password" => credentials.encrypted = true,
+                    _ => {}
+                }
+            }
+            self.credentials
+                .retain(|(known, _)| !known.eq_ignore_ascii_case(&name));
+            self.credentials.push((name, credentials));
+        }
+
+        Ok(())
+    }
+
+    /// Enabled sources with their credentials, highest priority first
+    pub fn package_sources(&self) -> Vec<PackageSource> {
+        let mut sources: Vec<&SourceEntry> = self
+            .sources
+            .iter()
+            .filter(|source| {
+                !self
+                    .disabled
+                    .iter()
+                    .any(|disabled| disabled.eq_ignore_ascii_case(&source.name))
+            })
+            .collect();
+        sources.sort_by_key(|source| std::cmp::Reverse(source.priority));
+
+        sources
+            .into_iter()
+            .map(|source| PackageSource {
+                name: source.name.clone(),
+                url: source.url.clone(),
+                protocol_version: source.protocol_version.clone(),
+                credentials: self
+                    .credentials
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&source.name))
+                    .map(|(_, credentials)| credentials.clone()),
+            })
+            .collect()
+    }
+
+    /// Point a NuGet-sourced `config` at the merged package sources
+    pub fn apply_to(&self, config: VersionDirConfig) -> Result<VersionDirConfig> {
+        let VersionSource::Nuget { package_id, .. } = &config.version_source else {
+            return Ok(config);
+        };
+
+        let mut feeds = Vec::new();
+        for source in self.package_sources() {
+            if !source.url.starts_with("https://") && !source.url.starts_with("http://") {
+                logger::Logger::debug(&format!(
+                    "NugetConfig: skipping local package source {} ({})",
+                    source.name, source.url
+                ));
+                continue;
+            }
+            let is_v3 = match source.protocol_version.as_deref() {
+                Some(version) => version == "3",
+                None => source.url.trim_end_matches('/').ends_with("index.json"),
+            };
+            if !is_v3 {
+                logger::Logger::warn(&format!(
+                    "NugetConfig: skipping package source {}: only NuGet v3 feeds are supported",
+                    source.name
+                ));
+                continue;
+            }
+
+            let auth = match &source.credentials {
+                Some(Credentials {
+                    username,
+                    password: Some(password),
+                    ..
+                }) => Some(HttpAuth::basic(username, password)),
+                Some(Credentials {
+                    encrypted: true, ..
+                }) => {
+                    logger::Logger::warn(&format!(
+                        "NugetConfig: the password for {} is encrypted and can't be used; use ClearTextPassword with an environment variable (%VAR%) instead",
+                        source.name
+                    ));
+                    None
+                }
+                _ => None,
+            };
+            feeds.push(NugetFeed {
+                url: source.url,
+                auth,
+            });
+        }
+
+        if feeds.is_empty() {
+            return Err(format!(
+                "nuget.config leaves no NuGet v3 package source to install {} from",
+                package_id
+            ));
+        }
+
+        let package_id = package_id.clone();
+        Ok(config.with_source(VersionSource::Nuget { feeds, package_id }))
+    }
+}
+
+/// Expand `%NAME%` references the way NuGet does, leaving unknown names untouched
+fn expand_env(value: &str, env: &[(String, String)]) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &after[..end];
+        match env.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some((_, value)) if !name.is_empty() => {
+                expanded.push_str(value);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                // Keep the first `%` and retry from the second, which may open a reference
+                expanded.push('%');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Source names become element names in `packageSourceCredentials`, with characters
+/// that aren't valid there encoded as `_xHHHH_`
+fn decode_element_name(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find("_x") {
+        decoded.push_str(&rest[..start]);
+        let encoded = rest[start + 2..]
+            .get(..5)
+            .filter(|candidate| candidate.ends_with('_'))
+            .and_then(|candidate| u32::from_str_radix(&candidate[..4], 16).ok())
+            .and_then(char::from_u32);
+        match encoded {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[start + 7..];
+            }
+            None => {
+                decoded.push_str("_x");
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_config_files_by_priority() {
+        let env = vec![("FEED_PAT".to_string(), "secret".to_string())];
+        let mut config = NugetConfig::with_defaults();
+        config
+            .apply(
+                r#"<configuration>
+                  <packageSources>
+                    <add key="Shared Feed" value="https://shared.example/v3/index.json" />
+                    <add key="old" value="https://old.example/v3/index.json" />
+                  </packageSources>
+                </configuration>"#,
+                &env,
+            )
+            .unwrap();
+        config
+            .apply(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+                <configuration>
+                  <packageSources>
+                    <clear />
+                    <add key="internal" value="https://internal.example/v3/index.json" />
+                    <add key="Shared Feed" value="https://shared.example/v3/index.json" />
+                    <add key="legacy" value="https://legacy.example/api/v2" />
+                  </packageSources>
+                  <disabledPackageSources>
+                    <add key="legacy" value="true" />
+                  </disabledPackageSources>
+                  <packageSourceCredentials>
+                    <Shared_x0020_Feed>
+                      <add key="Username" value="build" />
+                      <add key="ClearTextPassword" value="%FEED_PAT%" />
+                    </Shared_x0020_Feed>
+                  </packageSourceCredentials>
+                </configuration>"#,
+                &env,
+            )
+            .unwrap();
+
+        let sources = config.package_sources();
+        assert_eq!(
+            sources
+                .iter()
+                .map(|source| source.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["internal", "Shared Feed"]
+        );
+        assert_eq!(
+            sources[1].credentials,
+            Some(Credentials {
+                username: "build".to_string(),
+                password: Some("secret".to_string()),
+                encrypted: false,
+            })
+        );
+    }
+
+    #[test]
+    fn reads_the_worktree_config_over_the_defaults() {
+        let read_file = |name: &str| {
+            (name == "NuGet.Config").then(|| {
+                r#"<configuration>
+                  <packageSources>
+                    <add key="internal" value="https://internal.example/v3/index.json" />
+                  </packageSources>
+                </configuration>"#
+                    .to_string()
+            })
+        };
+        let config = NugetConfig::from_worktree(read_file, "/repo", &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            config
+                .package_sources()
+                .iter()
+                .map(|source| source.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["internal", "nuget.org"]
+        );
+
+        assert!(NugetConfig::from_worktree(|_| None, "/repo", &[])
+            .unwrap()
+            .is_none());
+        let error =
+            NugetConfig::from_worktree(|_| Some("<configuration".to_string()), "/repo", &[])
+                .err()
+                .unwrap();
+        assert!(error.starts_with("/repo/nuget.config: invalid nuget.config"));
+    }
+
+    #[test]
+    fn expands_environment_variables() {
+        let env = vec![("TOKEN".to_string(), "abc".to_string())];
+        assert_eq!(expand_env("%TOKEN%", &env), "abc");
+        assert_eq!(expand_env("100% %TOKEN%", &env), "100% abc");
+        assert_eq!(expand_env("%MISSING%", &env), "%MISSING%");
+    }
+}
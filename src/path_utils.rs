@@ -32,7 +32,10 @@ pub fn normalize_path_to_absolute(relative_path: &str) -> String {
             let mut path_str = absolute_path.to_string_lossy().to_string();
 
             // Fix the /C:/ prefix issue (convert to C:/)
-            if path_str.starts_with('/') && path_str.len() > 2 && path_str.chars().nth(2) == Some(':') {
+            if path_str.starts_with('/')
+                && path_str.len() > 2
+                && path_str.chars().nth(2) == Some(':')
+            {
                 path_str = path_str[1..].to_string();
             }
 
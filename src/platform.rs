@@ -0,0 +1,69 @@
+use std::sync::OnceLock;
+use zed_extension_api as zed;
+
+use crate::logger;
+
+/// C library a Linux host links native binaries against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    Glibc,
+    /// Alpine and other musl-based distributions, which can't load glibc binaries
+    Musl,
+}
+
+/// The host's C library on Linux, detected once per session; glibc elsewhere and
+/// whenever detection fails, since that is what most distributions use
+pub fn libc() -> Libc {
+    static LIBC: OnceLock<Libc> = OnceLock::new();
+    *LIBC.get_or_init(|| match zed::current_platform().0 {
+        zed::Os::Linux => detect_libc(),
+        _ => Libc::Glibc,
+    })
+}
+
+/// Ask `ldd` which C library it belongs to; musl's ldd prints its banner to stderr
+fn detect_libc() -> Libc {
+    match zed::process::Command::new("ldd").arg("--version").output() {
+        Ok(output) => {
+            let libc = parse_ldd_version(&format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+            logger::Logger::debug(&format!("platform::libc: detected {:?}", libc));
+            libc
+        }
+        Err(e) => {
+            logger::Logger::debug(&format!(
+                "platform::libc: failed to run ldd --version, assuming glibc: {}",
+                e
+            ));
+            Libc::Glibc
+        }
+    }
+}
+
+fn parse_ldd_version(output: &str) -> Libc {
+    if output.to_ascii_lowercase().contains("musl") {
+        Libc::Musl
+    } else {
+        Libc::Glibc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_musl_ldd() {
+        assert_eq!(
+            parse_ldd_version("musl libc (x86_64)\nVersion 1.2.4\nDynamic Program Loader\n"),
+            Libc::Musl
+        );
+        assert_eq!(
+            parse_ldd_version("ldd (Ubuntu GLIBC 2.35-0ubuntu3.8) 2.35\nCopyright (C) 2022\n"),
+            Libc::Glibc
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use zed_extension_api::{self as zed, LanguageServerId};
+
+use crate::logger;
+
+/// A step of installing a tool, as shown to the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallStatus {
+    CheckingForUpdate,
+    Downloading,
+    Extracting,
+    /// The tool is installed and ready; clears any earlier status
+    Ready,
+    /// The install failed, with a message fit for the user
+    Failed(String),
+}
+
+/// Where `BinaryManager` reports how an install is going
+pub trait InstallProgress {
+    fn report(&self, status: &InstallStatus);
+
+    /// The current step made headway: a chunk was downloaded or a file extracted
+    fn advance(&self) {}
+}
+
+/// Zed's installation status for a language server. Zed has no extracting state, so
+/// extraction shows as part of the download.
+impl InstallProgress for LanguageServerId {
+    fn report(&self, status: &InstallStatus) {
+        let status = match status {
+            InstallStatus::CheckingForUpdate => {
+                zed::LanguageServerInstallationStatus::CheckingForUpdate
+            }
+            InstallStatus::Downloading | InstallStatus::Extracting => {
+                zed::LanguageServerInstallationStatus::Downloading
+            }
+            InstallStatus::Ready => zed::LanguageServerInstallationStatus::None,
+            InstallStatus::Failed(message) => {
+                zed::LanguageServerInstallationStatus::Failed(message.clone())
+            }
+        };
+        zed::set_language_server_installation_status(self, &status);
+    }
+}
+
+/// Progress of a tool Zed has no status indicator for, such as the debug adapter. It is only
+/// logged: the language server's status belongs to the language server, and a failure reaches
+/// the user through the error of whatever needed the tool.
+pub struct ToolProgress<'a> {
+    pub tool: &'a str,
+}
+
+impl InstallProgress for ToolProgress<'_> {
+    fn report(&self, status: &InstallStatus) {
+        match status {
+            InstallStatus::Failed(message) => {
+                logger::Logger::error(&format!("{}: install failed: {}", self.tool, message))
+            }
+            status => logger::Logger::info(&format!("{}: {:?}", self.tool, status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_progress_stays_off_the_language_server() {
+        // Zed's status API isn't available outside the host, so reaching it would panic here
+        let progress = ToolProgress { tool: "netcoredbg" };
+        for status in [
+            InstallStatus::CheckingForUpdate,
+            InstallStatus::Downloading,
+            InstallStatus::Extracting,
+            InstallStatus::Ready,
+            InstallStatus::Failed("no network".to_string()),
+        ] {
+            progress.report(&status);
+            progress.advance();
+        }
+    }
+}
@@ -0,0 +1,1253 @@
+use std::fmt;
+use std::path::Path;
+use zed_extension_api as zed;
+
+use crate::checksum::{DigestAlgorithm, ExpectedDigest};
+use crate::dotnet::DotnetRuntimes;
+use crate::error::InstallError;
+use crate::file_system::FileSystem;
+use crate::http::{self, HttpClient};
+use crate::logger;
+use crate::nuget::FlatContainer;
+use crate::version::{Version, VersionReq};
+use crate::version_config::{auth_headers, HttpAuth, NugetFeed, VersionSource};
+
+/// Which versions of a tool are wanted
+pub struct VersionQuery<'a> {
+    /// Version requirement from settings; without one, only stable releases are wanted
+    pub requirement: Option<&'a VersionReq>,
+    /// Installed .NET runtimes, used to prefer package versions that can run here.
+    /// Empty when unknown or irrelevant (native tools).
+    pub runtimes: &'a DotnetRuntimes,
+}
+
+impl VersionQuery<'_> {
+    pub fn accepts(&self, version: &Version) -> bool {
+        match self.requirement {
+            Some(requirement) => requirement.matches(version),
+            None => !version.is_prerelease(),
+        }
+    }
+
+    /// The accepted versions among `versions`, newest first, as (parsed, raw) version
+    pub fn newest_first(&self, versions: &[String]) -> Vec<(Version, String)> {
+        let mut candidates: Vec<(Version, String)> = versions
+            .iter()
+            .filter_map(|raw_version| match Version::parse(raw_version) {
+                Ok(version) => Some((version, raw_version.clone())),
+                Err(e) => {
+                    logger::Logger::warn(&format!(
+                        "newest_first: ignoring remote release with unparseable version: {}",
+                        e
+                    ));
+                    None
+                }
+            })
+            .filter(|(version, _)| self.accepts(version))
+            .collect();
+        candidates.sort();
+        candidates.reverse();
+        candidates
+    }
+}
+
+/// A release asset to locate
+pub struct AssetRequest<'a> {
+    /// Tool the asset belongs to, for error messages
+    pub tool: &'a str,
+    pub version: &'a str,
+    /// Asset key of the platform, e.g. `linux-x64`
+    pub platform: &'a str,
+    /// File name of the asset
+    pub name: &'a str,
+}
+
+/// Where a located asset is fetched from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetLocation {
+    /// Downloaded over HTTP, with the credentials of the source that published it
+    Remote { url: String, auth: Option<HttpAuth> },
+    /// An archive on disk, inside the extension work directory
+    Local(String),
+}
+
+impl fmt::Display for AssetLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetLocation::Remote { url, .. } => f.write_str(url),
+            AssetLocation::Local(path) => f.write_str(path),
+        }
+    }
+}
+
+/// A located asset, and the digest it must match if the source publishes one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAsset {
+    pub location: AssetLocation,
+    pub digest: Option<ExpectedDigest>,
+}
+
+/// How sources reach the work directory and the network
+#[derive(Clone, Copy)]
+pub struct SourceHost<'a> {
+    pub fs: &'a dyn FileSystem,
+    pub http: &'a dyn HttpClient,
+}
+
+/// Where a tool's releases are published: lists the available versions and locates
+/// their assets
+pub trait ReleaseSource: fmt::Display {
+    /// Versions the source publishes, as it lists them, in any order
+    fn versions(&self, host: SourceHost) -> Result<Vec<String>, InstallError>;
+
+    /// Newest published version `query` accepts, as (parsed, raw) version
+    fn newest_version(
+        &self,
+        host: SourceHost,
+        query: &VersionQuery,
+    ) -> Result<Option<(Version, String)>, InstallError> {
+        Ok(query.newest_first(&self.versions(host)?).into_iter().next())
+    }
+
+    /// Locate an asset of a published version
+    fn resolve(
+        &self,
+        host: SourceHost,
+        asset: &AssetRequest,
+    ) -> Result<ResolvedAsset, InstallError>;
+}
+
+/// Turns a configured source into the backends that look it up; tests substitute fakes
+pub trait ReleaseLookup: Send + Sync {
+    /// Backends to query, in the order to try them
+    fn release_sources(&self, source: &VersionSource) -> Vec<Box<dyn ReleaseSource>>;
+}
+
+/// The real backends of each source
+pub struct Upstream;
+
+impl ReleaseLookup for Upstream {
+    fn release_sources(&self, source: &VersionSource) -> Vec<Box<dyn ReleaseSource>> {
+        source.release_sources()
+    }
+}
+
+#[cfg(test)]
+pub use fake::FakeReleases;
+
+#[cfg(test)]
+mod fake {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Base URL the fake's assets are served from
+    const FAKE_RELEASES_URL: &str = "https://releases.example";
+
+    #[derive(Default)]
+    struct State {
+        versions: Vec<String>,
+        digests: Vec<(String, ExpectedDigest)>,
+        unreachable: bool,
+    }
+
+    /// In-memory releases for tests, whatever the configured source. Assets are located
+    /// at `https://releases.example/<version>/<asset>`. Clones share the same releases.
+    #[derive(Clone, Default)]
+    pub struct FakeReleases {
+        state: Arc<Mutex<State>>,
+    }
+
+    impl FakeReleases {
+        pub fn new(versions: &[&str]) -> Self {
+            let releases = Self::default();
+            releases.state.lock().unwrap().versions =
+                versions.iter().map(|v| v.to_string()).collect();
+            releases
+        }
+
+        /// Publish another version
+        pub fn publish(&self, version: &str) {
+            self.state
+                .lock()
+                .unwrap()
+                .versions
+                .push(version.to_string());
+        }
+
+        /// Publish the digest of an asset, by its URL
+        pub fn publish_digest(&self, url: &str, digest: ExpectedDigest) {
+            self.state
+                .lock()
+                .unwrap()
+                .digests
+                .push((url.to_string(), digest));
+        }
+
+        /// Make every lookup fail as if the network were down
+        pub fn set_unreachable(&self, unreachable: bool) {
+            self.state.lock().unwrap().unreachable = unreachable;
+        }
+
+        pub fn asset_url(version: &str, name: &str) -> String {
+            format!("{}/{}/{}", FAKE_RELEASES_URL, version, name)
+        }
+
+        fn check_reachable(&self) -> Result<(), InstallError> {
+            match self.state.lock().unwrap().unreachable {
+                true => Err(InstallError::Network {
+                    url: FAKE_RELEASES_URL.to_string(),
+                    message: "network is unreachable".to_string(),
+                }),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl fmt::Display for FakeReleases {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "fake releases at {}", FAKE_RELEASES_URL)
+        }
+    }
+
+    impl ReleaseSource for FakeReleases {
+        fn versions(&self, _host: SourceHost) -> Result<Vec<String>, InstallError> {
+            self.check_reachable()?;
+            Ok(self.state.lock().unwrap().versions.clone())
+        }
+
+        fn resolve(
+            &self,
+            _host: SourceHost,
+            asset: &AssetRequest,
+        ) -> Result<ResolvedAsset, InstallError> {
+            self.check_reachable()?;
+            let url = Self::asset_url(asset.version, asset.name);
+            let state = self.state.lock().unwrap();
+            if !state
+                .versions
+                .iter()
+                .any(|version| version == asset.version)
+            {
+                return Err(InstallError::HttpStatus { url, status: 404 });
+            }
+            let digest = state
+                .digests
+                .iter()
+                .find(|(digest_url, _)| *digest_url == url)
+                .map(|(_, digest)| digest.clone());
+            Ok(ResolvedAsset {
+                location: AssetLocation::Remote { url, auth: None },
+                digest,
+            })
+        }
+    }
+
+    impl ReleaseLookup for FakeReleases {
+        fn release_sources(&self, _source: &VersionSource) -> Vec<Box<dyn ReleaseSource>> {
+            vec![Box::new(self.clone())]
+        }
+    }
+}
+
+/// Releases of a GitHub repository, on github.com or GitHub Enterprise
+pub struct GithubReleases {
+    pub api_url: Option<String>,
+    pub repo: String,
+    pub auth: Option<HttpAuth>,
+}
+
+impl fmt::Display for GithubReleases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.api_url {
+            None => write!(f, "GitHub ({})", self.repo),
+            Some(api_url) => write!(f, "GitHub ({} at {})", self.repo, api_url),
+        }
+    }
+}
+
+impl GithubReleases {
+    /// Published releases, newest first as GitHub lists them, with `v` prefixes dropped.
+    /// Pages are read until one satisfies `enough`.
+    fn releases(
+        &self,
+        http: &dyn HttpClient,
+        enough: impl Fn(&[GithubReleaseEntry]) -> bool,
+    ) -> Result<Vec<GithubReleaseEntry>, InstallError> {
+        github_releases(
+            self.api_url.as_deref(),
+            &self.repo,
+            |url| fetch_text(http, url, self.auth.as_ref()),
+            enough,
+        )
+    }
+}
+
+impl ReleaseSource for GithubReleases {
+    /// Every release but those GitHub marks as prereleases
+    fn versions(&self, host: SourceHost) -> Result<Vec<String>, InstallError> {
+        Ok(self
+            .releases(host.http, |_| false)?
+            .into_iter()
+            .filter(|release| !release.prerelease)
+            .map(|release| release.version)
+            .collect())
+    }
+
+    /// The latest release, unless the requirement rules it out. Only then are releases
+    /// listed, up to the first page with a match; prereleases count once a requirement
+    /// asks for versions.
+    fn newest_version(
+        &self,
+        host: SourceHost,
+        query: &VersionQuery,
+    ) -> Result<Option<(Version, String)>, InstallError> {
+        let latest = github_release(
+            host.http,
+            self.api_url.as_deref(),
+            &self.repo,
+            None,
+            self.auth.as_ref(),
+        )?;
+        let latest = query
+            .newest_first(&[latest.version.trim_start_matches('v').to_string()])
+            .into_iter()
+            .next();
+        if latest.is_some() || query.requirement.is_none() {
+            return Ok(latest);
+        }
+
+        let accepted = |releases: &[GithubReleaseEntry]| {
+            let versions: Vec<String> = releases
+                .iter()
+                .map(|release| release.version.clone())
+                .collect();
+            query.newest_first(&versions).into_iter().next()
+        };
+        Ok(accepted(
+            &self.releases(host.http, |page| accepted(page).is_some())?,
+        ))
+    }
+
+    fn resolve(
+        &self,
+        host: SourceHost,
+        asset: &AssetRequest,
+    ) -> Result<ResolvedAsset, InstallError> {
+        // Fetch the release for the resolved version, which may be pinned
+        let release = github_release_for_version(
+            host.http,
+            self.api_url.as_deref(),
+            &self.repo,
+            asset.version,
+            self.auth.as_ref(),
+        )?;
+
+        let download_url = release
+            .assets
+            .iter()
+            .find(|candidate| candidate.name == asset.name)
+            .map(|candidate| candidate.download_url.clone())
+            .ok_or_else(|| {
+                // A release may predate musl builds; say so rather than leave the
+                // user to spot the missing asset in the list
+                let musl_note = if asset.platform.contains("musl") {
+                    format!(" ({} {} has no musl build)", asset.tool, asset.version)
+                } else {
+                    String::new()
+                };
+                InstallError::UnsupportedPlatform(format!(
+                    "no compatible {} asset found for platform '{}'{}. available: [{}]",
+                    asset.tool,
+                    asset.platform,
+                    musl_note,
+                    release
+                        .assets
+                        .iter()
+                        .map(|a| a.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })?;
+
+        Ok(ResolvedAsset {
+            digest: github_release_asset_digest(
+                host.http,
+                &release,
+                &download_url,
+                self.auth.as_ref(),
+            )?,
+            location: AssetLocation::Remote {
+                url: download_url,
+                auth: self.auth.clone(),
+            },
+        })
+    }
+}
+
+/// All published versions of a package across NuGet v3 feeds, in priority order
+pub struct NugetFeeds {
+    pub feeds: Vec<NugetFeed>,
+    pub package_id: String,
+}
+
+impl fmt::Display for NugetFeeds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "NuGet ({} at {})",
+            self.package_id,
+            self.feeds
+                .iter()
+                .map(|feed| feed.url.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl NugetFeeds {
+    /// Credentials to send with a request to `url`. Feeds only get their own credentials,
+    /// matched by host, so one feed's password never reaches another.
+    pub fn auth_for(&self, url: &str) -> Option<&HttpAuth> {
+        let host = url_host(url)?;
+        self.feeds
+            .iter()
+            .find(|feed| url_host(&feed.url).as_ref() == Some(&host))
+            .and_then(|feed| feed.auth.as_ref())
+    }
+
+    /// Each reachable feed with the versions it lists; an error only when none is reachable
+    fn list<'a>(
+        &self,
+        http: &'a dyn HttpClient,
+    ) -> Result<Vec<(FlatContainer<'a>, Vec<String>)>, InstallError> {
+        let mut listed = Vec::new();
+        let mut errors = Vec::new();
+        for feed in &self.feeds {
+            match FlatContainer::open(http, &feed.url, &self.package_id, feed.auth.as_ref())
+                .and_then(|container| Ok((container.list_versions()?, container)))
+            {
+                Ok((versions, container)) => listed.push((container, versions)),
+                Err(e) => {
+                    logger::Logger::warn(&format!(
+                        "NugetFeeds::list: failed to list versions of {} in {}: {}",
+                        self.package_id, feed.url, e
+                    ));
+                    errors.push(e);
+                }
+            }
+        }
+
+        if listed.is_empty() && !errors.is_empty() {
+            return Err(InstallError::Network {
+                url: self.to_string(),
+                message: errors.join("; "),
+            });
+        }
+        Ok(listed)
+    }
+
+    /// The first feed that publishes `version` of the package; with a single feed, that feed
+    fn feed_with_version<'a>(
+        &self,
+        http: &'a dyn HttpClient,
+        version: &str,
+    ) -> Result<FlatContainer<'a>, InstallError> {
+        if let [feed] = self.feeds.as_slice() {
+            return FlatContainer::open(http, &feed.url, &self.package_id, feed.auth.as_ref())
+                .map_err(|message| InstallError::Network {
+                    url: feed.url.clone(),
+                    message,
+                });
+        }
+
+        self.list(http)?
+            .into_iter()
+            .find(|(_, versions)| versions.iter().any(|v| v.eq_ignore_ascii_case(version)))
+            .map(|(container, _)| container)
+            .ok_or_else(|| {
+                InstallError::VersionNotFound(format!(
+                    "no feed publishes {} {}",
+                    self.package_id, version
+                ))
+            })
+    }
+
+    /// Pick the newest candidate whose `.nuspec` targets a framework the installed runtimes
+    /// can run. Packages without framework groups (typical for dotnet tools) can't be
+    /// judged from the nuspec and are accepted; if nothing fits, the newest candidate wins.
+    fn first_runnable_package(
+        feeds: &[FlatContainer],
+        candidates: Vec<(Version, String, usize)>,
+        runtimes: &DotnetRuntimes,
+    ) -> Option<(Version, String)> {
+        // Each check is a request; only look a few releases back
+        const MAX_NUSPEC_CHECKS: usize = 5;
+
+        if runtimes.is_empty() {
+            return candidates
+                .into_iter()
+                .next()
+                .map(|(version, raw_version, _)| (version, raw_version));
+        }
+
+        for (version, raw_version, feed) in candidates.iter().take(MAX_NUSPEC_CHECKS) {
+            match feeds[*feed].nuspec(raw_version) {
+                Ok(nuspec)
+                    if nuspec.target_frameworks.is_empty()
+                        || nuspec
+                            .target_frameworks
+                            .iter()
+                            .any(|tfm| runtimes.can_run(tfm)) =>
+                {
+                    return Some((version.clone(), raw_version.clone()));
+                }
+                Ok(nuspec) => {
+                    logger::Logger::debug(&format!(
+                        "first_runnable_package: skipping {} {}: targets [{}], which no installed runtime can run",
+                        nuspec.id,
+                        nuspec.version,
+                        nuspec
+                            .target_frameworks
+                            .iter()
+                            .map(|tfm| tfm.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+                Err(e) => {
+                    // Can't tell; don't hold back an update over missing metadata
+                    logger::Logger::warn(&format!(
+                        "first_runnable_package: failed to read nuspec for {}: {}",
+                        raw_version, e
+                    ));
+                    return Some((version.clone(), raw_version.clone()));
+                }
+            }
+        }
+
+        logger::Logger::warn(
+            "first_runnable_package: no recent version targets an installed runtime, using the newest",
+        );
+        candidates
+            .into_iter()
+            .next()
+            .map(|(version, raw_version, _)| (version, raw_version))
+    }
+}
+
+impl ReleaseSource for NugetFeeds {
+    fn versions(&self, host: SourceHost) -> Result<Vec<String>, InstallError> {
+        let mut versions: Vec<String> = self
+            .list(host.http)?
+            .into_iter()
+            .flat_map(|(_, versions)| versions)
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    /// Like the dotnet CLI, the newest version across all feeds; a version published to
+    /// several feeds is attributed to the highest-priority one
+    fn newest_version(
+        &self,
+        host: SourceHost,
+        query: &VersionQuery,
+    ) -> Result<Option<(Version, String)>, InstallError> {
+        let mut containers = Vec::new();
+        let mut candidates: Vec<(Version, String, usize)> = Vec::new();
+        for (container, versions) in self.list(host.http)? {
+            for (version, raw_version) in query.newest_first(&versions) {
+                if !candidates.iter().any(|(known, _, _)| *known == version) {
+                    candidates.push((version, raw_version, containers.len()));
+                }
+            }
+            containers.push(container);
+        }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0));
+
+        Ok(Self::first_runnable_package(
+            &containers,
+            candidates,
+            query.runtimes,
+        ))
+    }
+
+    fn resolve(
+        &self,
+        host: SourceHost,
+        asset: &AssetRequest,
+    ) -> Result<ResolvedAsset, InstallError> {
+        let container = self.feed_with_version(host.http, asset.version)?;
+        let url = container.package_url(asset.version);
+        Ok(ResolvedAsset {
+            digest: container.package_digest(asset.version)?,
+            location: AssetLocation::Remote {
+                auth: self.auth_for(&url).cloned(),
+                url,
+            },
+        })
+    }
+}
+
+/// A plain HTTP directory laid out as `<url>/index.json` (`{"versions": [...]}`) and
+/// `<url>/<version>/<asset>`, or downloading from a URL template with `{url}`,
+/// `{version}` and `{asset}` placeholders
+pub struct HttpDirectory {
+    pub base_url: String,
+    pub url_template: Option<String>,
+    pub auth: Option<HttpAuth>,
+}
+
+impl fmt::Display for HttpDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP directory {}", self.base_url)
+    }
+}
+
+impl ReleaseSource for HttpDirectory {
+    fn versions(&self, host: SourceHost) -> Result<Vec<String>, InstallError> {
+        http_directory_versions(host.http, &self.base_url, self.auth.as_ref())
+    }
+
+    fn resolve(
+        &self,
+        host: SourceHost,
+        asset: &AssetRequest,
+    ) -> Result<ResolvedAsset, InstallError> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let url = match &self.url_template {
+            Some(template) => template
+                .replace("{url}", base_url)
+                .replace("{version}", asset.version)
+                .replace("{asset}", asset.name),
+            None => format!("{}/{}/{}", base_url, asset.version, asset.name),
+        };
+
+        // Optional sidecars next to the archive; a directory listing has nothing else
+        let digest = sidecar_digest(&url, asset.name, |sidecar_url| {
+            fetch_text(host.http, sidecar_url, self.auth.as_ref()).ok()
+        });
+        Ok(ResolvedAsset {
+            digest,
+            location: AssetLocation::Remote {
+                url,
+                auth: self.auth.clone(),
+            },
+        })
+    }
+}
+
+/// A folder of archives inside the extension work directory, laid out like an HTTP
+/// directory (`<path>/<version>/<asset>`) or holding archives named after their version
+pub struct LocalDirectory {
+    pub path: String,
+}
+
+impl fmt::Display for LocalDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "directory {}", self.path)
+    }
+}
+
+impl ReleaseSource for LocalDirectory {
+    fn versions(&self, host: SourceHost) -> Result<Vec<String>, InstallError> {
+        let path = self.path.trim_end_matches('/');
+        let entries = host
+            .fs
+            .read_dir(path)
+            .map_err(|e| InstallError::io(&self.path, &e))?;
+        let mut versions: Vec<String> = entries
+            .into_iter()
+            .filter_map(|name| {
+                if host.fs.is_dir(&format!("{}/{}", path, name)) {
+                    Version::parse(&name).is_ok().then_some(name)
+                } else {
+                    version_from_archive_name(&name)
+                }
+            })
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        Ok(versions)
+    }
+
+    fn resolve(
+        &self,
+        host: SourceHost,
+        asset: &AssetRequest,
+    ) -> Result<ResolvedAsset, InstallError> {
+        let path = self.path.trim_end_matches('/');
+        let mut candidates = vec![format!("{}/{}/{}", path, asset.version, asset.name)];
+        // A flat folder can only tell versions apart by the archive name
+        if version_from_archive_name(asset.name).as_deref() == Some(asset.version) {
+            candidates.push(format!("{}/{}", path, asset.name));
+        }
+
+        let archive_path = candidates
+            .into_iter()
+            .find(|candidate| host.fs.is_file(candidate))
+            .ok_or_else(|| {
+                InstallError::VersionNotFound(format!(
+                    "{} has no {} for {} {}",
+                    self.path, asset.name, asset.tool, asset.version
+                ))
+            })?;
+
+        let digest = sidecar_digest(&archive_path, asset.name, |sidecar_path| {
+            host.fs.read_to_string(sidecar_path).ok()
+        });
+        Ok(ResolvedAsset {
+            digest,
+            location: AssetLocation::Local(archive_path),
+        })
+    }
+}
+
+/// Find a version in file names like `csharp-ls.0.16.0.nupkg` or `netcoredbg-3.1.2.tar.gz`
+pub fn version_from_archive_name(archive_path: &str) -> Option<String> {
+    let file_name = Path::new(archive_path).file_name()?.to_str()?;
+    let stem = [".tar.gz", ".tgz", ".zip", ".nupkg"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(file_name);
+
+    // The version starts after the first separator that is followed by one
+    stem.match_indices(['.', '-', '_'])
+        .map(|(index, _)| &stem[index + 1..])
+        .find(|candidate| {
+            candidate.starts_with(|c: char| c.is_ascii_digit()) && Version::parse(candidate).is_ok()
+        })
+        .map(str::to_string)
+}
+
+/// Digest from a `.sha512` or `.sha256` sidecar next to an archive, read with `read`
+fn sidecar_digest(
+    archive: &str,
+    asset_name: &str,
+    read: impl Fn(&str) -> Option<String>,
+) -> Option<ExpectedDigest> {
+    [
+        (DigestAlgorithm::Sha512, "sha512"),
+        (DigestAlgorithm::Sha256, "sha256"),
+    ]
+    .into_iter()
+    .find_map(|(algorithm, extension)| {
+        let sidecar = format!("{}.{}", archive, extension);
+        let contents = read(&sidecar)?;
+        ExpectedDigest::from_checksum_file(algorithm, &contents, asset_name, &sidecar).ok()
+    })
+}
+
+/// Lowercased host (and port) of an http(s) URL
+fn url_host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    Some(host.to_ascii_lowercase())
+}
+
+/// Fetch a small text resource (checksum files, feed metadata)
+fn fetch_text(
+    http: &dyn HttpClient,
+    url: &str,
+    auth: Option<&HttpAuth>,
+) -> Result<String, InstallError> {
+    let response = http
+        .fetch(&http::get_request(url, auth_headers(auth)))
+        .map_err(|e| InstallError::from_fetch(url, &e))?;
+    String::from_utf8(response.body).map_err(|e| InstallError::UnexpectedContent {
+        url: url.to_string(),
+        message: format!("invalid UTF-8: {}", e),
+    })
+}
+
+/// Fetch a GitHub release: the one tagged `tag`, or the latest when `tag` is None.
+/// github.com without credentials goes through Zed's GitHub client; anything else
+/// (GitHub Enterprise, tokens) talks to the REST API directly.
+fn github_release(
+    http: &dyn HttpClient,
+    api_url: Option<&str>,
+    repo: &str,
+    tag: Option<&str>,
+    auth: Option<&HttpAuth>,
+) -> Result<zed::GithubRelease, InstallError> {
+    if api_url.is_none() && auth.is_none() {
+        return http.github_release(repo, tag).map_err(|e| {
+            InstallError::from_fetch(&format!("https://github.com/{}/releases", repo), &e)
+        });
+    }
+
+    let api_url = api_url
+        .unwrap_or("https://api.github.com")
+        .trim_end_matches('/');
+    let url = match tag {
+        Some(tag) => format!("{}/repos/{}/releases/tags/{}", api_url, repo, tag),
+        None => format!("{}/repos/{}/releases/latest", api_url, repo),
+    };
+    let release: zed::serde_json::Value = zed::serde_json::from_str(&fetch_text(http, &url, auth)?)
+        .map_err(|e| InstallError::UnexpectedContent {
+            url: url.clone(),
+            message: format!("invalid release JSON: {}", e),
+        })?;
+
+    let version = release
+        .get("tag_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            let message = release.get("message").and_then(|v| v.as_str());
+            InstallError::UnexpectedContent {
+                url: url.clone(),
+                message: format!("no release: {}", message.unwrap_or("no tag_name")),
+            }
+        })?;
+    let assets = release
+        .get("assets")
+        .and_then(|v| v.as_array())
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|asset| {
+                    Some(zed::GithubReleaseAsset {
+                        name: asset.get("name")?.as_str()?.to_string(),
+                        download_url: asset.get("browser_download_url")?.as_str()?.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(zed::GithubRelease {
+        version: version.to_string(),
+        assets,
+    })
+}
+
+/// Largest page the GitHub releases API serves
+const GITHUB_RELEASES_PER_PAGE: usize = 100;
+/// Pages of releases to read at most; older releases are still found by tag
+const MAX_GITHUB_RELEASE_PAGES: usize = 10;
+
+/// A published (non-draft) GitHub release
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GithubReleaseEntry {
+    /// Tag without its `v` prefix
+    version: String,
+    prerelease: bool,
+}
+
+/// List a repository's releases through the REST API, page by page, reading each page
+/// with `fetch` until one satisfies `enough`. Drafts are skipped. Zed's GitHub client
+/// only finds single releases, so github.com is listed through the REST API too.
+fn github_releases(
+    api_url: Option<&str>,
+    repo: &str,
+    fetch: impl Fn(&str) -> Result<String, InstallError>,
+    enough: impl Fn(&[GithubReleaseEntry]) -> bool,
+) -> Result<Vec<GithubReleaseEntry>, InstallError> {
+    let api_url = api_url
+        .unwrap_or("https://api.github.com")
+        .trim_end_matches('/');
+    let mut releases = Vec::new();
+    for page in 1..=MAX_GITHUB_RELEASE_PAGES {
+        let url = format!(
+            "{}/repos/{}/releases?per_page={}&page={}",
+            api_url, repo, GITHUB_RELEASES_PER_PAGE, page
+        );
+        let listed: zed::serde_json::Value =
+            zed::serde_json::from_str(&fetch(&url)?).map_err(|e| {
+                InstallError::UnexpectedContent {
+                    url: url.clone(),
+                    message: format!("invalid release list JSON: {}", e),
+                }
+            })?;
+        let listed = listed
+            .as_array()
+            .ok_or_else(|| InstallError::UnexpectedContent {
+                url: url.clone(),
+                message: format!(
+                    "no release list: {}",
+                    listed
+                        .get("message")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("not an array")
+                ),
+            })?;
+
+        let page: Vec<GithubReleaseEntry> = listed
+            .iter()
+            .filter_map(|release| {
+                if release.get("draft").and_then(|v| v.as_bool()) == Some(true) {
+                    return None;
+                }
+                Some(GithubReleaseEntry {
+                    version: release
+                        .get("tag_name")?
+                        .as_str()?
+                        .trim_start_matches('v')
+                        .to_string(),
+                    prerelease: release
+                        .get("prerelease")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                })
+            })
+            .collect();
+        let done = enough(&page) || listed.len() < GITHUB_RELEASES_PER_PAGE;
+        releases.extend(page);
+        if done {
+            break;
+        }
+    }
+    Ok(releases)
+}
+
+/// Fetch the GitHub release for a version, whose tag may or may not carry a `v` prefix
+fn github_release_for_version(
+    http: &dyn HttpClient,
+    api_url: Option<&str>,
+    repo: &str,
+    version: &str,
+    auth: Option<&HttpAuth>,
+) -> Result<zed::GithubRelease, InstallError> {
+    github_release(http, api_url, repo, Some(&format!("v{}", version)), auth)
+        .or_else(|_| github_release(http, api_url, repo, Some(version), auth))
+}
+
+/// Versions listed in an HTTP directory's `index.json`
+fn http_directory_versions(
+    http: &dyn HttpClient,
+    base_url: &str,
+    auth: Option<&HttpAuth>,
+) -> Result<Vec<String>, InstallError> {
+    let url = format!("{}/index.json", base_url.trim_end_matches('/'));
+    let index: zed::serde_json::Value = zed::serde_json::from_str(&fetch_text(http, &url, auth)?)
+        .map_err(|e| InstallError::UnexpectedContent {
+        url: url.clone(),
+        message: format!("invalid version index: {}", e),
+    })?;
+
+    index
+        .get("versions")
+        .and_then(|versions| versions.as_array())
+        .map(|versions| {
+            versions
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| InstallError::UnexpectedContent {
+            url: url.clone(),
+            message: "version index has no versions array".to_string(),
+        })
+}
+
+/// Look up the SHA-256 of a GitHub release asset from a `<asset>.sha256` sidecar or a
+/// `SHA256SUMS`/`checksums.txt` file published in the same release
+fn github_release_asset_digest(
+    http: &dyn HttpClient,
+    release: &zed::GithubRelease,
+    download_url: &str,
+    auth: Option<&HttpAuth>,
+) -> Result<Option<ExpectedDigest>, InstallError> {
+    let Some(asset) = release
+        .assets
+        .iter()
+        .find(|asset| asset.download_url == download_url)
+    else {
+        return Ok(None);
+    };
+
+    let sidecar_name = format!("{}.sha256", asset.name);
+    let checksum_asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == sidecar_name)
+        .or_else(|| {
+            release.assets.iter().find(|a| {
+                matches!(
+                    a.name.to_ascii_lowercase().as_str(),
+                    "sha256sums" | "sha256sums.txt" | "checksums.txt"
+                )
+            })
+        });
+
+    match checksum_asset {
+        Some(checksum_asset) => {
+            let contents = fetch_text(http, &checksum_asset.download_url, auth)?;
+            Ok(Some(ExpectedDigest::from_checksum_file(
+                DigestAlgorithm::Sha256,
+                &contents,
+                &asset.name,
+                &checksum_asset.name,
+            )?))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+    use crate::http::FakeHttpClient;
+
+    #[test]
+    fn reads_versions_from_archive_names() {
+        let version = |name| version_from_archive_name(name);
+        assert_eq!(
+            version("downloads/csharp-ls.0.16.0.nupkg"),
+            Some("0.16.0".to_string())
+        );
+        assert_eq!(
+            version("netcoredbg-3.1.2.tar.gz"),
+            Some("3.1.2".to_string())
+        );
+        assert_eq!(
+            version("csharp-ls.1.0.0-beta.2.nupkg"),
+            Some("1.0.0-beta.2".to_string())
+        );
+        assert_eq!(version("netcoredbg-linux-x64.tar.gz"), None);
+    }
+
+    #[test]
+    fn finds_archives_in_local_directories() {
+        let fs = MemoryFileSystem::new();
+        fs.put("mirror/3.1.2/netcoredbg-linux-x64.tar.gz", b"archive");
+        fs.put(
+            "mirror/3.1.2/netcoredbg-linux-x64.tar.gz.sha256",
+            &[b'0'; 64],
+        );
+        fs.put("mirror/csharp-ls.0.16.0.nupkg", b"package");
+        let http = FakeHttpClient::new();
+        let host = SourceHost {
+            fs: &fs,
+            http: &http,
+        };
+        let source = LocalDirectory {
+            path: "mirror/".to_string(),
+        };
+
+        let mut versions = source.versions(host).unwrap();
+        versions.sort();
+        assert_eq!(versions, vec!["0.16.0", "3.1.2"]);
+        let request = |version, name| AssetRequest {
+            tool: "netcoredbg",
+            version,
+            platform: "linux-x64",
+            name,
+        };
+        let resolved = source
+            .resolve(host, &request("3.1.2", "netcoredbg-linux-x64.tar.gz"))
+            .unwrap();
+        assert_eq!(
+            resolved.location,
+            AssetLocation::Local("mirror/3.1.2/netcoredbg-linux-x64.tar.gz".to_string())
+        );
+        assert_eq!(resolved.digest.unwrap().algorithm, DigestAlgorithm::Sha256);
+        assert!(source
+            .resolve(host, &request("0.16.0", "csharp-ls.0.16.0.nupkg"))
+            .is_ok());
+        assert!(matches!(
+            source.resolve(host, &request("3.1.3", "netcoredbg-linux-x64.tar.gz")),
+            Err(InstallError::VersionNotFound(_))
+        ));
+        assert!(http.requests().is_empty());
+    }
+
+    #[test]
+    fn looks_up_github_releases_sparingly() {
+        const REPO: &str = "marcptrs/netcoredbg";
+        let http = FakeHttpClient::new();
+        let download = |tag: &str, name: &str| {
+            format!(
+                "https://github.com/{}/releases/download/{}/{}",
+                REPO, tag, name
+            )
+        };
+        let asset_url = download("v3.1.2", "netcoredbg-linux-x64.tar.gz");
+        let sums_url = download("v3.1.2", "SHA256SUMS");
+        http.publish_github_release(
+            REPO,
+            "v3.1.2",
+            &[
+                ("netcoredbg-linux-x64.tar.gz", &asset_url),
+                ("SHA256SUMS", &sums_url),
+            ],
+        );
+        http.publish_github_release(REPO, "v3.2.0", &[]);
+        http.serve(
+            &format!(
+                "https://api.github.com/repos/{}/releases?per_page=100&page=1",
+                REPO
+            ),
+            br#"[
+                {"tag_name": "v3.2.0"},
+                {"tag_name": "v3.1.3-rc.1", "prerelease": true},
+                {"tag_name": "v3.1.2"},
+                {"tag_name": "v3.0.0"}
+            ]"#,
+        );
+        http.serve(
+            &sums_url,
+            format!("{}  netcoredbg-linux-x64.tar.gz\n", "ab".repeat(32)).as_bytes(),
+        );
+        let fs = MemoryFileSystem::new();
+        let host = SourceHost {
+            fs: &fs,
+            http: &http,
+        };
+        let source = GithubReleases {
+            api_url: None,
+            repo: REPO.to_string(),
+            auth: None,
+        };
+        let runtimes = DotnetRuntimes::default();
+        let newest = |requirement: Option<&VersionReq>| {
+            let query = VersionQuery {
+                requirement,
+                runtimes: &runtimes,
+            };
+            source.newest_version(host, &query).unwrap().unwrap().1
+        };
+        let latest = format!("https://github.com/{}/releases/latest", REPO);
+
+        // Unpinned, and with a range the latest release meets, Zed's GitHub client answers
+        assert_eq!(newest(None), "3.2.0");
+        assert_eq!(newest(Some(&VersionReq::parse("^3.1").unwrap())), "3.2.0");
+        assert_eq!(http.requests(), vec![latest.clone(), latest.clone()]);
+
+        // Older releases are only listed for a range the latest release misses
+        let version = newest(Some(&VersionReq::parse(">=3.1, <3.2").unwrap()));
+        assert_eq!(version, "3.1.2");
+        assert_eq!(http.requests().len(), 4);
+
+        let resolved = source
+            .resolve(
+                host,
+                &AssetRequest {
+                    tool: "netcoredbg",
+                    version: &version,
+                    platform: "linux-x64",
+                    name: "netcoredbg-linux-x64.tar.gz",
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            resolved.location,
+            AssetLocation::Remote {
+                url: asset_url.clone(),
+                auth: None
+            }
+        );
+        assert_eq!(resolved.digest.unwrap().source, "SHA256SUMS");
+        assert_eq!(
+            http.requests()[4..],
+            [
+                format!("https://github.com/{}/releases/tag/v3.1.2", REPO),
+                sums_url
+            ]
+        );
+    }
+
+    #[test]
+    fn pages_through_github_releases() {
+        // A full first page of old patch releases, then a short second page
+        let first: Vec<String> = (0..GITHUB_RELEASES_PER_PAGE)
+            .map(|patch| format!(r#"{{"tag_name": "v3.0.{}"}}"#, patch))
+            .collect();
+        let second = r#"[
+            {"tag_name": "v3.2.0-rc.1", "prerelease": true},
+            {"tag_name": "v3.1.9", "draft": true},
+            {"tag_name": "3.1.2", "prerelease": false}
+        ]"#;
+        let fetch = |url: &str| match url {
+            "https://api.github.com/repos/marcptrs/netcoredbg/releases?per_page=100&page=1" => {
+                Ok(format!("[{}]", first.join(",")))
+            }
+            "https://api.github.com/repos/marcptrs/netcoredbg/releases?per_page=100&page=2" => {
+                Ok(second.to_string())
+            }
+            url => panic!("unexpected request for {}", url),
+        };
+
+        let releases = github_releases(None, "marcptrs/netcoredbg", fetch, |_| false).unwrap();
+        assert_eq!(releases.len(), GITHUB_RELEASES_PER_PAGE + 2);
+        assert_eq!(
+            releases[GITHUB_RELEASES_PER_PAGE..],
+            [
+                GithubReleaseEntry {
+                    version: "3.2.0-rc.1".to_string(),
+                    prerelease: true
+                },
+                GithubReleaseEntry {
+                    version: "3.1.2".to_string(),
+                    prerelease: false
+                },
+            ]
+        );
+
+        // Older minor versions are listed too, so a range pin finds its newest match
+        let versions: Vec<String> = releases
+            .into_iter()
+            .map(|release| release.version)
+            .collect();
+        let requirement = VersionReq::parse(">=3.1, <3.2").unwrap();
+        let query = VersionQuery {
+            requirement: Some(&requirement),
+            runtimes: &DotnetRuntimes::default(),
+        };
+        assert_eq!(query.newest_first(&versions)[0].1, "3.1.2");
+
+        // Listing stops at the first page with what's wanted
+        let pages = std::cell::Cell::new(0);
+        let listed = github_releases(
+            None,
+            "marcptrs/netcoredbg",
+            |url| {
+                pages.set(pages.get() + 1);
+                fetch(url)
+            },
+            |page| !page.is_empty(),
+        )
+        .unwrap();
+        assert_eq!((pages.get(), listed.len()), (1, GITHUB_RELEASES_PER_PAGE));
+
+        // An error object instead of a list is no release list
+        let error = github_releases(
+            None,
+            "marcptrs/netcoredbg",
+            |_| Ok(r#"{"message": "Not Found"}"#.to_string()),
+            |_| false,
+        );
+        assert!(matches!(
+            error,
+            Err(InstallError::UnexpectedContent { message, .. }) if message.contains("Not Found")
+        ));
+    }
+
+    #[test]
+    fn scopes_feed_credentials_to_their_host() {
+        let source = NugetFeeds {
+            feeds: vec![
+                NugetFeed {
+                    url: "https://api.nuget.org/v3/index.json".to_string(),
+                    auth: None,
+                },
+                NugetFeed {
+                    url: "https://Internal.example:8443/v3/index.json".to_string(),
+                    auth: Some(HttpAuth::bearer("token")),
+                },
+            ],
+            package_id: "csharp-ls".to_string(),
+        };
+
+        assert!(source
+            .auth_for("https://internal.example:8443/flat/csharp-ls/1.0.0/csharp-ls.1.0.0.nupkg")
+            .is_some());
+        assert!(source
+            .auth_for("https://api.nuget.org/v3-flatcontainer/csharp-ls/index.json")
+            .is_none());
+    }
+}
@@ -0,0 +1,331 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use zed_extension_api::{self as zed, serde_json::Value, Result};
+
+use crate::dotnet::{self, DotnetRuntimes};
+use crate::error::InstallError;
+use crate::file_system::FileSystem;
+use crate::nuget::NUGET_ORG_FLAT_CONTAINER;
+use crate::platform::{self, Libc};
+use crate::validation::{ExpectedFile, FileFormat};
+use crate::version_config::VersionSource;
+
+/// Manifests of the tools the extension installs
+const BUILTIN_TOOLS: &str = include_str!("tools.json");
+
+/// Asset key matching every platform, for platform-independent packages
+const ANY_PLATFORM: &str = "any";
+
+/// Where a tool's versions are published
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SourceSpec {
+    /// Releases of a GitHub repository; `api_url` for GitHub Enterprise
+    Github {
+        repo: String,
+        api_url: Option<String>,
+    },
+    /// A NuGet package, on nuget.org unless `url` names another feed
+    Nuget {
+        package: String,
+        url: Option<String>,
+    },
+    /// An HTTP directory listing versions in `<url>/index.json`, downloading from
+    /// `template` (`{url}`, `{version}` and `{asset}` placeholders) or `<url>/<version>/<asset>`
+    Url {
+        url: String,
+        template: Option<String>,
+    },
+    /// A folder of archives inside the extension work directory
+    Directory { path: String },
+}
+
+/// Archive type of a tool's release assets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ArchiveKind {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+/// A file an install must contain; `path` may use the entry point placeholders
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRule {
+    pub path: String,
+    pub format: FileFormat,
+}
+
+/// Declarative description of a tool: where it's published, which asset to install on
+/// each platform, and what a complete install looks like
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolManifest {
+    /// Also the prefix of the tool's version directories
+    pub name: String,
+    pub source: SourceSpec,
+    /// Release asset name by platform (`linux-x64`, `linux-musl-arm64`, `osx-arm64`,
+    /// `win-x86`, ...) or `any`; `{version}` is replaced with the version
+    pub assets: BTreeMap<String, String>,
+    /// Archive type, when the asset name and contents don't tell
+    #[serde(default)]
+    pub archive: Option<ArchiveKind>,
+    /// Binary to run, relative to the version directory. `{exe}` is `.exe` on Windows;
+    /// `{framework}` is the newest framework folder the installed runtimes can run.
+    pub entry_point: String,
+    /// Files checked after extraction. `{entry_point}` is the chosen binary and
+    /// `{entry_point_stem}` the same without its extension.
+    #[serde(default)]
+    pub validate: Vec<ValidationRule>,
+    /// Refuse to install from the manifest's own `source` when it publishes no checksum.
+    /// Sources configured in settings are exempt: mirrors and private feeds often have none.
+    #[serde(default)]
+    pub require_digest: bool,
+}
+
+impl ToolManifest {
+    /// The built-in manifest of `name`, with `overrides` from settings merged on top
+    pub fn builtin(name: &str, overrides: Option<&Value>) -> Result<Self> {
+        let tools: Vec<Value> = zed::serde_json::from_str(BUILTIN_TOOLS)
+            .map_err(|e| format!("invalid built-in tool manifests: {}", e))?;
+        let mut manifest = tools
+            .into_iter()
+            .find(|tool| tool.get("name").and_then(Value::as_str) == Some(name))
+            .ok_or_else(|| format!("no built-in tool manifest for {}", name))?;
+
+        if let Some(overrides) = overrides {
+            if overrides.get("name").is_some() {
+                return Err(format!("the name of {} can't be overridden", name));
+            }
+            merge(&mut manifest, overrides);
+        }
+        zed::serde_json::from_value(manifest)
+            .map_err(|e| format!("invalid tool manifest for {}: {}", name, e))
+    }
+
+    pub fn version_source(&self) -> VersionSource {
+        match &self.source {
+            SourceSpec::Github { repo, api_url } => VersionSource::GithubRelease {
+                api_url: api_url.clone(),
+                repo: repo.clone(),
+                auth: None,
+            },
+            SourceSpec::Nuget { package, url } => {
+                VersionSource::nuget(url.as_deref().unwrap_or(NUGET_ORG_FLAT_CONTAINER), package)
+            }
+            SourceSpec::Url { url, template } => VersionSource::HttpDirectory {
+                base_url: url.clone(),
+                url_template: template.clone(),
+                auth: None,
+            },
+            SourceSpec::Directory { path } => VersionSource::Directory { path: path.clone() },
+        }
+    }
+
+    /// Asset key of the current platform. Linux hosts on musl only get musl builds, and
+    /// are only probed for when the tool publishes any.
+    pub fn platform(&self) -> std::result::Result<String, InstallError> {
+        if self.assets.contains_key(ANY_PLATFORM) {
+            return Ok(ANY_PLATFORM.to_string());
+        }
+
+        let (os, arch) = zed::current_platform();
+        let musl = os == zed::Os::Linux
+            && self.assets.keys().any(|key| key.contains("-musl-"))
+            && platform::libc() == Libc::Musl;
+        let key = platform_key(os, arch, musl);
+        if self.assets.contains_key(&key) {
+            Ok(key)
+        } else {
+            Err(InstallError::UnsupportedPlatform(format!(
+                "{} is not published for {}; available: {}",
+                self.name,
+                key,
+                self.assets.keys().cloned().collect::<Vec<_>>().join(", ")
+            )))
+        }
+    }
+
+    /// Release asset name of `version` for a platform key
+    pub fn asset_name(&self, version: &str, platform: &str) -> Option<String> {
+        self.assets
+            .get(platform)
+            .or_else(|| self.assets.get(ANY_PLATFORM))
+            .map(|template| template.replace("{version}", version))
+    }
+
+    /// Binary to run in `version_dir`, picking the framework folder for `runtimes`.
+    /// When no framework folder contains the binary, `{framework}` is left in place.
+    pub fn entry_point(
+        &self,
+        fs: &dyn FileSystem,
+        version_dir: &str,
+        runtimes: &DotnetRuntimes,
+    ) -> String {
+        let mut entry_point = self.entry_point.clone();
+        if entry_point.contains("{exe}") {
+            let exe = match zed::current_platform().0 {
+                zed::Os::Windows => ".exe",
+                _ => "",
+            };
+            entry_point = entry_point.replace("{exe}", exe);
+        }
+
+        let entry_point = match entry_point.split_once("{framework}") {
+            Some((parent, file)) => {
+                let parent = parent.trim_end_matches('/');
+                let file = file.trim_start_matches('/');
+                let frameworks =
+                    dotnet::framework_folders(fs, &format!("{}/{}", version_dir, parent), file);
+                let framework = runtimes
+                    .best_framework(&frameworks)
+                    .map_or("{framework}", |(_, folder)| folder.as_str());
+                format!("{}/{}/{}", parent, framework, file)
+            }
+            None => entry_point,
+        };
+        format!("{}/{}", version_dir, entry_point)
+    }
+
+    /// Files a complete install with `binary` (relative to the version directory) contains
+    pub fn expected_files(&self, binary: &str) -> Vec<ExpectedFile> {
+        let stem = binary
+            .rsplit_once('.')
+            .filter(|(_, extension)| !extension.contains('/'))
+            .map_or(binary, |(stem, _)| stem);
+        self.validate
+            .iter()
+            .map(|rule| {
+                let path = rule
+                    .path
+                    .replace("{entry_point_stem}", stem)
+                    .replace("{entry_point}", binary);
+                ExpectedFile::new(&path, rule.format)
+            })
+            .collect()
+    }
+}
+
+/// Asset key for a platform, e.g. `linux-musl-x64` or `osx-arm64`
+fn platform_key(os: zed::Os, arch: zed::Architecture, musl: bool) -> String {
+    let os = match os {
+        zed::Os::Linux if musl => "linux-musl",
+        zed::Os::Linux => "linux",
+        zed::Os::Mac => "osx",
+        zed::Os::Windows => "win",
+    };
+    let arch = match arch {
+        zed::Architecture::Aarch64 => "arm64",
+        zed::Architecture::X86 => "x86",
+        zed::Architecture::X8664 => "x64",
+    };
+    format!("{}-{}", os, arch)
+}
+
+/// Merge `overrides` into `base`: objects key by key, anything else replaced. An object
+/// with a different `type` (a source of another kind) replaces the whole object.
+fn merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides))
+            if overrides
+                .get("type")
+                .is_none_or(|kind| base.get("type") == Some(kind)) =>
+        {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn loads_builtin_manifests() {
+        let csharp_ls = ToolManifest::builtin("csharp-language-server", None).unwrap();
+        assert_eq!(
+            csharp_ls.asset_name("0.16.0", "any").as_deref(),
+            Some("csharp-ls.0.16.0.nupkg")
+        );
+        assert_eq!(
+            csharp_ls.expected_files("tools/net9.0/any/CSharpLanguageServer.dll")[1],
+            ExpectedFile::new(
+                "tools/net9.0/any/CSharpLanguageServer.runtimeconfig.json",
+                FileFormat::Any
+            )
+        );
+
+        let netcoredbg = ToolManifest::builtin("netcoredbg", None).unwrap();
+        assert_eq!(
+            netcoredbg.version_source(),
+            VersionSource::GithubRelease {
+                api_url: None,
+                repo: "marcptrs/netcoredbg".to_string(),
+                auth: None
+            }
+        );
+        assert_eq!(
+            netcoredbg
+                .asset_name(
+                    "3.1.2",
+                    &platform_key(zed::Os::Linux, zed::Architecture::X8664, true)
+                )
+                .as_deref(),
+            Some("netcoredbg-linux-musl-x64.tar.gz")
+        );
+        assert_eq!(netcoredbg.asset_name("3.1.2", "linux-musl-x86"), None);
+    }
+
+    #[test]
+    fn merges_settings_overrides() {
+        let overrides = zed::serde_json::json!({
+            "source": { "type": "url", "url": "https://mirror.example/netcoredbg" },
+            "assets": { "linux-riscv64": "netcoredbg-linux-riscv64.tar.gz" }
+        });
+        let netcoredbg = ToolManifest::builtin("netcoredbg", Some(&overrides)).unwrap();
+        assert_eq!(
+            netcoredbg.source,
+            SourceSpec::Url {
+                url: "https://mirror.example/netcoredbg".to_string(),
+                template: None
+            }
+        );
+        assert_eq!(netcoredbg.assets.len(), 12);
+
+        let renamed = zed::serde_json::json!({ "name": "other" });
+        assert!(ToolManifest::builtin("netcoredbg", Some(&renamed)).is_err());
+        let unknown = zed::serde_json::json!({ "entrypoint": "x" });
+        assert!(ToolManifest::builtin("netcoredbg", Some(&unknown)).is_err());
+    }
+
+    #[test]
+    fn resolves_framework_entry_points() {
+        let fs = MemoryFileSystem::new();
+        let dir = "csharp-language-server-0.16.0";
+        for framework in ["net8.0", "net9.0"] {
+            fs.put(
+                &format!("{}/tools/{}/any/CSharpLanguageServer.dll", dir, framework),
+                b"MZ",
+            );
+        }
+
+        let csharp_ls = ToolManifest::builtin("csharp-language-server", None).unwrap();
+        let runtimes = DotnetRuntimes::parse(
+            "Microsoft.NETCore.App 8.0.11 [/usr/share/dotnet/shared/Microsoft.NETCore.App]\n",
+        );
+        assert_eq!(
+            csharp_ls.entry_point(&fs, dir, &runtimes),
+            format!("{}/tools/net8.0/any/CSharpLanguageServer.dll", dir)
+        );
+    }
+}
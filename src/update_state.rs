@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zed_extension_api::{self as zed, Result};
+
+use crate::file_system::FileSystem;
+
+/// State file in the extension work directory
+const UPDATE_STATE_FILE: &str = ".update-state.json";
+
+/// Outcome of the last successful update check of one tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+    /// Newest version the source offered
+    pub version: String,
+    /// Source that was checked; a check against a different source doesn't count
+    pub source: String,
+}
+
+/// Last update check per tool, persisted across Zed processes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateState {
+    #[serde(default)]
+    pub tools: BTreeMap<String, UpdateCheck>,
+}
+
+impl UpdateState {
+    /// Load the state file; a missing or unreadable file is an empty state
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        fs.read_to_string(UPDATE_STATE_FILE)
+            .ok()
+            .and_then(|contents| zed::serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, fs: &dyn FileSystem) -> Result<()> {
+        let contents = zed::serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize update state: {}", e))?;
+        // Write then rename, so a concurrent reader never sees a partial file
+        let temp_file = format!("{}.tmp", UPDATE_STATE_FILE);
+        fs.write(&temp_file, contents.as_bytes())
+            .and_then(|()| fs.rename(&temp_file, UPDATE_STATE_FILE))
+            .map_err(|e| format!("failed to write {}: {}", UPDATE_STATE_FILE, e))
+    }
+
+    /// Version found by a check of `source` within the last `interval`, if any
+    pub fn fresh_version(&self, tool: &str, source: &str, interval: Duration) -> Option<&str> {
+        let check = self.tools.get(tool)?;
+        let age = now().checked_sub(check.checked_at)?;
+        (check.source == source && age < interval.as_secs()).then_some(check.version.as_str())
+    }
+
+    pub fn record(&mut self, tool: &str, source: &str, version: &str) {
+        self.tools.insert(
+            tool.to_string(),
+            UpdateCheck {
+                checked_at: now(),
+                version: version.to_string(),
+                source: source.to_string(),
+            },
+        );
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Parse an update check interval: `always`, `hourly`, `daily`, `weekly`, `never`,
+/// or a number with an `m`, `h` or `d` suffix (`12h`)
+pub fn parse_interval(value: &str) -> Result<Duration> {
+    const HOUR: u64 = 60 * 60;
+    let value = value.trim().to_ascii_lowercase();
+    let seconds = match value.as_str() {
+        "always" => 0,
+        "hourly" => HOUR,
+        "daily" => 24 * HOUR,
+        "weekly" => 7 * 24 * HOUR,
+        "never" => u64::MAX,
+        _ => {
+            let split = value.len().saturating_sub(1);
+            let (count, unit) = value.split_at(split);
+            let unit = match unit {
+                "m" => 60,
+                "h" => HOUR,
+                "d" => 24 * HOUR,
+                _ => return Err(format!("unknown interval '{}'", value)),
+            };
+            let count: u64 = count
+                .trim()
+                .parse()
+                .map_err(|_| format!("unknown interval '{}'", value))?;
+            count.saturating_mul(unit)
+        }
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("always").unwrap(), Duration::ZERO);
+        assert_eq!(
+            parse_interval("Daily").unwrap(),
+            Duration::from_secs(86_400)
+        );
+        assert_eq!(parse_interval("12h").unwrap(), Duration::from_secs(43_200));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1_800));
+        assert!(parse_interval("soon").is_err());
+        assert!(parse_interval("h").is_err());
+    }
+
+    #[test]
+    fn only_fresh_checks_of_the_same_source_count() {
+        let mut state = UpdateState::default();
+        state.record("netcoredbg", "GitHub (marcptrs/netcoredbg)", "3.1.2");
+
+        let day = Duration::from_secs(86_400);
+        assert_eq!(
+            state.fresh_version("netcoredbg", "GitHub (marcptrs/netcoredbg)", day),
+            Some("3.1.2")
+        );
+        assert_eq!(
+            state.fresh_version("netcoredbg", "GitHub (mirror/netcoredbg)", day),
+            None
+        );
+        assert_eq!(
+            state.fresh_version("netcoredbg", "GitHub (marcptrs/netcoredbg)", Duration::ZERO),
+            None
+        );
+    }
+}
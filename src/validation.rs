@@ -0,0 +1,270 @@
+use serde::Deserialize;
+use std::io::Read;
+use zed_extension_api as zed;
+
+use crate::file_system::FileSystem;
+
+/// What a required file must look like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    /// Any non-empty file
+    Any,
+    /// A .NET assembly: a PE image with a CLR header
+    ManagedAssembly,
+    /// A native executable for the current platform (PE, ELF or Mach-O)
+    NativeExecutable,
+}
+
+/// A file a complete install must contain, relative to the version directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedFile {
+    pub path: String,
+    pub format: FileFormat,
+}
+
+impl ExpectedFile {
+    pub fn new(path: &str, format: FileFormat) -> Self {
+        Self {
+            path: path.to_string(),
+            format,
+        }
+    }
+}
+
+/// A regular file written during extraction, relative to the destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedFile {
+    pub path: String,
+    pub size: u64,
+    /// The archive entry carries an executable permission bit
+    pub executable: bool,
+}
+
+/// Why an install failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationFailure {
+    MissingFile(String),
+    EmptyFile(String),
+    InvalidHeader {
+        path: String,
+        reason: String,
+    },
+    /// A file listed in the archive is missing from disk or has the wrong size
+    ExtractionMismatch {
+        path: String,
+        expected_size: u64,
+        actual_size: Option<u64>,
+    },
+}
+
+impl std::fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationFailure::MissingFile(path) => write!(f, "{} is missing", path),
+            ValidationFailure::EmptyFile(path) => write!(f, "{} is empty", path),
+            ValidationFailure::InvalidHeader { path, reason } => {
+                write!(f, "{} is malformed: {}", path, reason)
+            }
+            ValidationFailure::ExtractionMismatch {
+                path,
+                expected_size,
+                actual_size: None,
+            } => write!(
+                f,
+                "{} ({} bytes in the archive) was not extracted",
+                path, expected_size
+            ),
+            ValidationFailure::ExtractionMismatch {
+                path,
+                expected_size,
+                actual_size: Some(actual_size),
+            } => write!(
+                f,
+                "{} is {} bytes on disk but {} bytes in the archive",
+                path, actual_size, expected_size
+            ),
+        }
+    }
+}
+
+/// Check an install in `dir` against the files its config requires and the files its
+/// archive listed. Returns every failure found, empty when the install is sound.
+pub fn validate_install(
+    fs: &dyn FileSystem,
+    dir: &str,
+    expected: &[ExpectedFile],
+    extracted: &[ExtractedFile],
+) -> Vec<ValidationFailure> {
+    let mut failures = Vec::new();
+
+    for file in extracted {
+        let actual_size = fs
+            .metadata(&format!("{}/{}", dir, file.path))
+            .ok()
+            .filter(|stat| stat.is_file())
+            .map(|stat| stat.len);
+        if actual_size != Some(file.size) {
+            failures.push(ValidationFailure::ExtractionMismatch {
+                path: file.path.clone(),
+                expected_size: file.size,
+                actual_size,
+            });
+        }
+    }
+
+    for file in expected {
+        let path = format!("{}/{}", dir, file.path);
+        match fs.metadata(&path) {
+            Ok(stat) if !stat.is_file() => {
+                failures.push(ValidationFailure::MissingFile(file.path.clone()))
+            }
+            Ok(stat) if stat.len == 0 => {
+                failures.push(ValidationFailure::EmptyFile(file.path.clone()))
+            }
+            Ok(_) => {
+                if let Err(reason) = check_format(fs, &path, file.format) {
+                    failures.push(ValidationFailure::InvalidHeader {
+                        path: file.path.clone(),
+                        reason,
+                    });
+                }
+            }
+            Err(_) => failures.push(ValidationFailure::MissingFile(file.path.clone())),
+        }
+    }
+
+    failures
+}
+
+fn check_format(fs: &dyn FileSystem, path: &str, format: FileFormat) -> Result<(), String> {
+    // Headers this code looks at all fit in the first few KB
+    let mut header = Vec::with_capacity(4096);
+    fs.open(path)
+        .and_then(|file| file.take(4096).read_to_end(&mut header))
+        .map_err(|e| format!("unreadable: {}", e))?;
+
+    match format {
+        FileFormat::Any => Ok(()),
+        FileFormat::ManagedAssembly => check_managed_assembly(&header),
+        FileFormat::NativeExecutable => match zed::current_platform().0 {
+            zed::Os::Windows => check_pe(&header).map(|_| ()),
+            zed::Os::Linux => check_elf(&header),
+            zed::Os::Mac => check_mach_o(&header),
+        },
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Validate the DOS and PE signatures; returns the offset of the optional header
+fn check_pe(header: &[u8]) -> Result<usize, String> {
+    if !header.starts_with(b"MZ") {
+        return Err("no DOS (MZ) signature".to_string());
+    }
+    let pe_offset = read_u32(header, 0x3c).ok_or("truncated DOS header")? as usize;
+    if header.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0".as_slice()) {
+        return Err("no PE signature".to_string());
+    }
+    // Signature (4 bytes) and COFF file header (20 bytes) precede the optional header
+    Ok(pe_offset + 24)
+}
+
+/// A PE image whose CLR runtime header (data directory 14) is present
+fn check_managed_assembly(header: &[u8]) -> Result<(), String> {
+    let optional_header = check_pe(header)?;
+    let data_directories = match read_u16(header, optional_header) {
+        Some(0x10b) => optional_header + 96,
+        Some(0x20b) => optional_header + 112,
+        _ => return Err("unknown PE optional header".to_string()),
+    };
+    let clr_header = data_directories + 14 * 8;
+    match (
+        read_u32(header, clr_header),
+        read_u32(header, clr_header + 4),
+    ) {
+        (Some(rva), Some(size)) if rva != 0 && size != 0 => Ok(()),
+        (Some(_), Some(_)) => Err("PE image has no CLR header".to_string()),
+        _ => Err("truncated PE optional header".to_string()),
+    }
+}
+
+fn check_elf(header: &[u8]) -> Result<(), String> {
+    if !header.starts_with(b"\x7fELF") {
+        return Err("no ELF signature".to_string());
+    }
+    // e_type: executable or position-independent (shared object)
+    match read_u16(header, 16) {
+        Some(2) | Some(3) => Ok(()),
+        Some(other) => Err(format!("ELF file of type {} is not executable", other)),
+        None => Err("truncated ELF header".to_string()),
+    }
+}
+
+fn check_mach_o(header: &[u8]) -> Result<(), String> {
+    const MAGICS: [[u8; 4]; 4] = [
+        [0xcf, 0xfa, 0xed, 0xfe], // 64-bit, little-endian
+        [0xce, 0xfa, 0xed, 0xfe], // 32-bit, little-endian
+        [0xca, 0xfe, 0xba, 0xbe], // universal binary
+        [0xfe, 0xed, 0xfa, 0xcf], // 64-bit, big-endian
+    ];
+    if MAGICS.iter().any(|magic| header.starts_with(magic)) {
+        Ok(())
+    } else {
+        Err("no Mach-O signature".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal PE32+ headers, optionally with a CLR data directory
+    fn pe_image(managed: bool) -> Vec<u8> {
+        let mut image = vec![0u8; 512];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        let optional_header = 0x80 + 24;
+        image[optional_header..optional_header + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        if managed {
+            let clr_header = optional_header + 112 + 14 * 8;
+            image[clr_header..clr_header + 4].copy_from_slice(&0x2008u32.to_le_bytes());
+            image[clr_header + 4..clr_header + 8].copy_from_slice(&0x48u32.to_le_bytes());
+        }
+        image
+    }
+
+    #[test]
+    fn recognizes_managed_assemblies() {
+        assert_eq!(check_managed_assembly(&pe_image(true)), Ok(()));
+        assert_eq!(
+            check_managed_assembly(&pe_image(false)),
+            Err("PE image has no CLR header".to_string())
+        );
+        assert!(check_managed_assembly(b"\x7fELF").is_err());
+        assert!(check_pe(&pe_image(false)).is_ok());
+    }
+
+    #[test]
+    fn recognizes_native_executables() {
+        let mut elf = b"\x7fELF".to_vec();
+        elf.resize(18, 0);
+        elf[16] = 3;
+        assert_eq!(check_elf(&elf), Ok(()));
+        elf[16] = 1;
+        assert!(check_elf(&elf).is_err());
+        assert_eq!(check_mach_o(&[0xcf, 0xfa, 0xed, 0xfe, 0, 0]), Ok(()));
+        assert!(check_mach_o(b"MZ").is_err());
+    }
+}
@@ -0,0 +1,496 @@
+use std::cmp::Ordering;
+use std::fmt;
+use zed_extension_api::Result;
+
+/// A single dot-separated prerelease identifier (`alpha`, `1`, `rc`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identifier {
+    Numeric(u64),
+    AlphaNumeric(String),
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Numeric identifiers always have lower precedence than alphanumeric ones
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::AlphaNumeric(_)) => Ordering::Less,
+            (Identifier::AlphaNumeric(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::AlphaNumeric(a), Identifier::AlphaNumeric(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::AlphaNumeric(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Semantic version (https://semver.org) as used by GitHub release tags and NuGet packages.
+///
+/// Parsing is lenient about a leading `v` and missing minor/patch components
+/// (`v1.2` reads as `1.2.0`), since tags in the wild are not always strict SemVer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pre: Vec<Identifier>,
+    build: Vec<String>,
+}
+
+impl Version {
+    pub fn parse(text: &str) -> Result<Self> {
+        let trimmed = text.trim();
+        let trimmed = trimmed
+            .strip_prefix('v')
+            .or_else(|| trimmed.strip_prefix('V'))
+            .unwrap_or(trimmed);
+
+        let (rest, build) = match trimmed.split_once('+') {
+            Some((rest, build)) => (rest, Some(build)),
+            None => (trimmed, None),
+        };
+        let (core, pre) = match rest.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (rest, None),
+        };
+
+        let mut numbers = core.split('.');
+        let mut next_number = |name: &str, required: bool| -> Result<u64> {
+            match numbers.next() {
+                Some(part) => parse_numeric(part).ok_or_else(|| {
+                    format!(
+                        "invalid {} component '{}' in version '{}'",
+                        name, part, text
+                    )
+                }),
+                None if required => Err(format!("invalid version '{}'", text)),
+                None => Ok(0),
+            }
+        };
+        let major = next_number("major", true)?;
+        let minor = next_number("minor", false)?;
+        let patch = next_number("patch", false)?;
+        if numbers.next().is_some() {
+            return Err(format!("too many components in version '{}'", text));
+        }
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|part| {
+                    if part.is_empty() || !is_identifier(part) {
+                        Err(format!(
+                            "invalid prerelease '{}' in version '{}'",
+                            pre, text
+                        ))
+                    } else if let Some(n) = parse_numeric(part) {
+                        Ok(Identifier::Numeric(n))
+                    } else {
+                        Ok(Identifier::AlphaNumeric(part.to_string()))
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        let build = match build {
+            Some(build) => build
+                .split('.')
+                .map(|part| {
+                    if part.is_empty() || !is_identifier(part) {
+                        Err(format!(
+                            "invalid build metadata '{}' in version '{}'",
+                            build, text
+                        ))
+                    } else {
+                        Ok(part.to_string())
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// SemVer precedence, which ignores build metadata
+    pub fn cmp_precedence(&self, other: &Self) -> Ordering {
+        self.major
+            .cmp(&other.major)
+            .then(self.minor.cmp(&other.minor))
+            .then(self.patch.cmp(&other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release has higher precedence than any of its prereleases
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+/// Digits only; leading zeros are tolerated since some tags use them (`2024.01.5`)
+fn parse_numeric(part: &str) -> Option<u64> {
+    if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    part.parse().ok()
+}
+
+fn is_identifier(part: &str) -> bool {
+    part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Build metadata carries no precedence; it only breaks ties so Ord agrees with Eq
+        self.cmp_precedence(other)
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(|p| p.to_string()).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Comparison operator of a single version requirement clause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    /// `~1.2.3`: patch updates only
+    Tilde,
+    /// `^1.2.3`: no changes to the left-most non-zero component
+    Caret,
+}
+
+/// One clause of a [`VersionReq`], e.g. `>=0.9` or `1.2.*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+    /// Number of numeric components written (`1.2` = 2), so `1.2.*` and `~1.2` know what is free
+    specified: usize,
+}
+
+impl Comparator {
+    fn parse(text: &str, requirement: &str) -> Result<Self> {
+        let text = text.trim();
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .iter()
+        .find_map(|(prefix, op)| text.strip_prefix(prefix).map(|rest| (*op, rest.trim())))
+        .unwrap_or((Op::Exact, text));
+
+        // A trailing wildcard (`1.2.*`, `1.x`, `*`) leaves the remaining components free,
+        // which is the same as an exact match on a partial version
+        let core_end = rest.find(['-', '+']).unwrap_or(rest.len());
+        let mut parts: Vec<&str> = rest[..core_end].split('.').collect();
+        let is_wildcard = |part: &&str| matches!(*part, "*" | "x" | "X");
+        let has_wildcard = parts.last().is_some_and(is_wildcard);
+        if has_wildcard {
+            parts.pop();
+        }
+        if parts.iter().any(is_wildcard)
+            || (has_wildcard && (op != Op::Exact || core_end != rest.len()))
+        {
+            return Err(format!(
+                "invalid wildcard in version requirement '{}'",
+                requirement
+            ));
+        }
+
+        let specified = parts.len().min(3);
+        let version = if specified == 0 {
+            Version::parse("0")?
+        } else {
+            let text = format!("{}{}", parts.join("."), &rest[core_end..]);
+            Version::parse(&text)
+                .map_err(|e| format!("invalid version requirement '{}': {}", requirement, e))?
+        };
+
+        Ok(Self {
+            op,
+            version,
+            specified,
+        })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let required = &self.version;
+        let core = (version.major, version.minor, version.patch);
+        let upper = match self.op {
+            Op::Greater => return version.cmp_precedence(required).is_gt(),
+            Op::GreaterEq => return version.cmp_precedence(required).is_ge(),
+            Op::Less => return version.cmp_precedence(required).is_lt(),
+            Op::LessEq => return version.cmp_precedence(required).is_le(),
+            Op::Exact if self.specified == 3 => {
+                return version.cmp_precedence(required).is_eq();
+            }
+            // Partial exact versions (`1.2`) match anything with that prefix
+            Op::Exact => match self.specified {
+                0 => return true,
+                1 => (required.major + 1, 0, 0),
+                _ => (required.major, required.minor + 1, 0),
+            },
+            Op::Tilde => match self.specified {
+                1 => (required.major + 1, 0, 0),
+                _ => (required.major, required.minor + 1, 0),
+            },
+            Op::Caret => {
+                if required.major > 0 || self.specified == 1 {
+                    (required.major + 1, 0, 0)
+                } else if required.minor > 0 || self.specified == 2 {
+                    (0, required.minor + 1, 0)
+                } else {
+                    (0, 0, required.patch + 1)
+                }
+            }
+        };
+        version.cmp_precedence(required).is_ge() && core < upper
+    }
+}
+
+/// A version constraint from settings: an exact version (`0.16.0`), a range
+/// (`>=0.15, <0.17`, `^0.16`, `~0.16.1`) or a wildcard (`0.16.*`).
+///
+/// Unlike Cargo, a bare version means exactly that version, since the point of
+/// the setting is reproducibility. Prereleases only match when a clause names one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+    source: String,
+}
+
+impl VersionReq {
+    pub fn parse(text: &str) -> Result<Self> {
+        let comparators = text
+            .split(',')
+            .map(|clause| Comparator::parse(clause, text))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            comparators,
+            source: text.trim().to_string(),
+        })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        if !version.pre.is_empty()
+            && !self.comparators.iter().any(|c| {
+                !c.version.pre.is_empty()
+                    && (c.version.major, c.version.minor, c.version.patch)
+                        == (version.major, version.minor, version.patch)
+            })
+        {
+            return false;
+        }
+
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+
+    /// The single version this requirement pins to, if it is an exact version
+    pub fn exact(&self) -> Option<&Version> {
+        match self.comparators.as_slice() {
+            [comparator] if comparator.op == Op::Exact && comparator.specified >= 3 => {
+                Some(&comparator.version)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_components() {
+        let version = v("1.2.3-beta.1+build.5");
+        assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+        assert!(!version.pre.is_empty());
+        assert_eq!(version.to_string(), "1.2.3-beta.1+build.5");
+    }
+
+    #[test]
+    fn accepts_tag_prefix_and_short_versions() {
+        assert_eq!(v("v0.9.1"), v("0.9.1"));
+        assert_eq!(v("1.2").to_string(), "1.2.0");
+        assert_eq!(v("3").to_string(), "3.0.0");
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        for text in [
+            "",
+            "abc",
+            "1.x.0",
+            "1.2.3.4",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "1.2.3+",
+            "1.-2",
+        ] {
+            assert!(Version::parse(text).is_err(), "{} should not parse", text);
+        }
+    }
+
+    #[test]
+    fn compares_numerically_not_lexically() {
+        assert!(v("0.10.0") > v("0.9.0"));
+        assert!(v("1.0.10") > v("1.0.9"));
+        assert!(v("10.0.0") > v("9.99.99"));
+    }
+
+    #[test]
+    fn release_outranks_prerelease() {
+        assert!(v("1.0.0") > v("1.0.0-beta"));
+        assert!(v("1.0.0-rc.1") < v("1.0.0"));
+        assert!(v("1.0.1-alpha") > v("1.0.0"));
+    }
+
+    #[test]
+    fn orders_prerelease_identifiers() {
+        // The example chain from the SemVer spec, section 11
+        let chain = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in chain.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn build_metadata_does_not_affect_precedence() {
+        assert!(v("1.0.0+build.2") < v("1.0.1"));
+        assert!(v("1.0.0+20240101") > v("1.0.0-rc.1"));
+        assert_eq!(v("1.0.0+a").cmp(&v("1.0.0+b")), Ordering::Less);
+        assert_ne!(v("1.0.0+a"), v("1.0.0"));
+    }
+
+    #[test]
+    fn max_picks_highest() {
+        let versions = ["0.9.0", "0.10.0", "1.0.0-beta", "0.10.1"];
+        let max = versions.iter().map(|s| v(s)).max().unwrap();
+        assert_eq!(max, v("1.0.0-beta"));
+    }
+
+    fn req(text: &str) -> VersionReq {
+        VersionReq::parse(text).unwrap()
+    }
+
+    #[test]
+    fn bare_version_is_exact() {
+        let pinned = req("0.16.0");
+        assert_eq!(pinned.exact(), Some(&v("0.16.0")));
+        assert!(pinned.matches(&v("0.16.0")));
+        assert!(pinned.matches(&v("0.16.0+build.1")));
+        assert!(!pinned.matches(&v("0.16.1")));
+        assert_eq!(req("=v0.16.0").exact(), Some(&v("0.16.0")));
+        assert_eq!(req("0.16").exact(), None);
+    }
+
+    #[test]
+    fn matches_ranges_and_wildcards() {
+        let range = req(">=0.15, <0.17");
+        assert!(range.matches(&v("0.15.0")));
+        assert!(range.matches(&v("0.16.9")));
+        assert!(!range.matches(&v("0.17.0")));
+        assert!(!range.matches(&v("0.14.9")));
+
+        assert!(req("0.16.*").matches(&v("0.16.4")));
+        assert!(!req("0.16.*").matches(&v("0.17.0")));
+        assert!(req("1.x").matches(&v("1.9.0")));
+        assert!(req("*").matches(&v("42.0.0")));
+
+        assert!(req("~0.16.1").matches(&v("0.16.9")));
+        assert!(!req("~0.16.1").matches(&v("0.17.0")));
+        assert!(req("^1.2").matches(&v("1.9.0")));
+        assert!(!req("^1.2").matches(&v("2.0.0")));
+        assert!(req("^0.16.2").matches(&v("0.16.3")));
+        assert!(!req("^0.16.2").matches(&v("0.17.0")));
+    }
+
+    #[test]
+    fn prereleases_only_match_when_requested() {
+        assert!(!req(">=1.0.0").matches(&v("2.0.0-beta")));
+        assert!(req(">=2.0.0-alpha").matches(&v("2.0.0-beta")));
+        assert!(req("2.0.0-beta").matches(&v("2.0.0-beta")));
+    }
+
+    #[test]
+    fn rejects_malformed_requirements() {
+        for text in ["", ">=", "1.*.2", ">=1.*", "^x", "1.2.*-beta"] {
+            assert!(
+                VersionReq::parse(text).is_err(),
+                "{} should not parse",
+                text
+            );
+        }
+    }
+}
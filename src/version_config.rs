@@ -1,171 +1,284 @@
-/// Configuration for version directory download
-pub struct VersionDirConfig {
-    /// Directory prefix (e.g., "vscode-csharp" or "netcoredbg")
-    pub prefix: String,
-    /// GitHub repository (e.g., "dotnet/vscode-csharp")
-    pub github_repo: String,
-    /// Function to get the binary path relative to version_dir
-    pub get_binary_path: fn(&str) -> String,
-    /// Binary name for logging
-    pub binary_name_for_logging: String,
-    /// Function to resolve download URL - fetches from GitHub releases or uses fallback
-    /// Returns download_url given (version, platform)
-    pub get_download_url: fn(&str, &str) -> Result<String, String>,
-    /// Function to get the platform string for this package
-    /// Different packages use different naming conventions (darwin vs osx, win32 vs win, etc.)
-    pub get_platform_string: fn() -> Result<String, String>,
+use crate::base64;
+use crate::dotnet::DotnetRuntimes;
+use crate::error::InstallError;
+use crate::file_system::FileSystem;
+use crate::install_manifest::InstallManifest;
+use crate::release_source::{
+    GithubReleases, HttpDirectory, LocalDirectory, NugetFeeds, ReleaseSource,
+};
+use crate::tool_manifest::ToolManifest;
+use crate::validation::ExpectedFile;
+use zed_extension_api as zed;
+
+/// Where the published versions of a tool are discovered and downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSource {
+    /// Releases of a GitHub repository (e.g. "marcptrs/netcoredbg"), on github.com
+    /// unless `api_url` points at a GitHub Enterprise (or compatible) REST API
+    GithubRelease {
+        api_url: Option<String>,
+        repo: String,
+        auth: Option<HttpAuth>,
+    },
+    /// All published versions of a package across NuGet v3 feeds, in priority order
+    Nuget {
+        feeds: Vec<NugetFeed>,
+        package_id: String,
+    },
+    /// A plain HTTP directory laid out as `<url>/index.json` (`{"versions": [...]}`)
+    /// and `<url>/<version>/<archive name>`, or downloads from `url_template` with
+    /// `{url}`, `{version}` and `{asset}` placeholders
+    HttpDirectory {
+        base_url: String,
+        url_template: Option<String>,
+        auth: Option<HttpAuth>,
+    },
+    /// A folder of archives inside the extension work directory
+    Directory { path: String },
+    /// `primary`, or `fallback` when `primary` can't be reached
+    Fallback {
+        primary: Box<VersionSource>,
+        fallback: Box<VersionSource>,
+    },
+}
+
+/// A NuGet v3 feed, given by its service index (`.../index.json`) or flat container URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NugetFeed {
+    pub url: String,
+    pub auth: Option<HttpAuth>,
+}
+
+impl VersionSource {
+    pub fn nuget(feed_url: &str, package_id: &str) -> Self {
+        Self::Nuget {
+            feeds: vec![NugetFeed {
+                url: feed_url.to_string(),
+                auth: None,
+            }],
+            package_id: package_id.to_string(),
+        }
+    }
+
+    /// Backends to look up and download the tool from, in the order to try them
+    pub fn release_sources(&self) -> Vec<Box<dyn ReleaseSource>> {
+        match self {
+            VersionSource::GithubRelease {
+                api_url,
+                repo,
+                auth,
+            } => vec![Box::new(GithubReleases {
+                api_url: api_url.clone(),
+                repo: repo.clone(),
+                auth: auth.clone(),
+            })],
+            VersionSource::Nuget { feeds, package_id } => vec![Box::new(NugetFeeds {
+                feeds: feeds.clone(),
+                package_id: package_id.clone(),
+            })],
+            VersionSource::HttpDirectory {
+                base_url,
+                url_template,
+                auth,
+            } => vec![Box::new(HttpDirectory {
+                base_url: base_url.clone(),
+                url_template: url_template.clone(),
+                auth: auth.clone(),
+            })],
+            VersionSource::Directory { path } => {
+                vec![Box::new(LocalDirectory { path: path.clone() })]
+            }
+            VersionSource::Fallback { primary, fallback } => {
+                let mut sources = primary.release_sources();
+                sources.extend(fallback.release_sources());
+                sources
+            }
+        }
+    }
+
+    /// The sources to try in turn: a fallback's primary, then what it falls back to
+    pub fn alternatives(&self) -> Vec<&VersionSource> {
+        match self {
+            VersionSource::Fallback { primary, fallback } => {
+                let mut sources = primary.alternatives();
+                sources.extend(fallback.alternatives());
+                sources
+            }
+            source => vec![source],
+        }
+    }
+
+    /// Folders the source reads archives from, which cleanups must leave alone
+    pub fn local_paths(&self) -> Vec<&str> {
+        match self {
+            VersionSource::Directory { path } => vec![path.as_str()],
+            VersionSource::Fallback { primary, fallback } => {
+                let mut paths = primary.local_paths();
+                paths.extend(fallback.local_paths());
+                paths
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
-/// Builder for creating version configs
-pub struct VersionConfigBuilder {
-    prefix: String,
-    github_repo: String,
-    get_binary_path: fn(&str) -> String,
-    binary_name_for_logging: String,
-    get_download_url: fn(&str, &str) -> Result<String, String>,
-    get_platform_string: fn() -> Result<String, String>,
+impl std::fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sources: Vec<String> = self
+            .release_sources()
+            .iter()
+            .map(|source| source.to_string())
+            .collect();
+        f.write_str(&sources.join(", falling back to "))
+    }
 }
 
-impl VersionConfigBuilder {
-    pub fn new(prefix: &str, github_repo: &str) -> Self {
+/// Credentials for a custom source, sent as a request header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpAuth {
+    pub header: String,
+    pub value: String,
+}
+
+impl HttpAuth {
+    /// `Authorization: Bearer <token>`
+    pub fn bearer(token: &str) -> Self {
         Self {
-            prefix: prefix.to_string(),
-            github_repo: github_repo.to_string(),
-            get_binary_path: |_| String::new(),
-            binary_name_for_logging: String::new(),
-            get_download_url: |_, _| Err("not configured".to_string()),
-            get_platform_string: || Err("not configured".to_string()),
+            header: "Authorization".to_string(),
+            value: format!("Bearer {}", token),
         }
     }
 
-    pub fn get_binary_path(mut self, builder: fn(&str) -> String) -> Self {
-        self.get_binary_path = builder;
-        self
+    /// `Authorization: Basic <base64(username:password)>`
+    pub fn basic(username: &str, password: &str) -> Self {
+        Self {
+            header: "Authorization".to_string(),
+            value: format!(
+                "Basic {}",
+                base64::encode(format!("{}:{}", username, password).as_bytes())
+            ),
+        }
     }
+}
 
-    pub fn binary_name_for_logging(mut self, name: &str) -> Self {
-        self.binary_name_for_logging = name.to_string();
-        self
+/// Request headers carrying `auth`, if any
+pub fn auth_headers(auth: Option<&HttpAuth>) -> Vec<(String, String)> {
+    auth.map(|auth| vec![(auth.header.clone(), auth.value.clone())])
+        .unwrap_or_default()
+}
+
+/// A tool to install, described by its manifest
+pub struct VersionDirConfig {
+    /// Directory prefix (e.g., "csharp-language-server" or "netcoredbg")
+    pub prefix: String,
+    /// Where available versions are looked up and downloaded from; the manifest's
+    /// source unless a setting overrides it
+    pub version_source: VersionSource,
+    /// Settings path users configure this tool under, for error messages
+    pub settings_key: String,
+    pub manifest: ToolManifest,
+}
+
+impl VersionDirConfig {
+    pub fn new(manifest: ToolManifest, settings_key: &str) -> Self {
+        Self {
+            prefix: manifest.name.clone(),
+            version_source: manifest.version_source(),
+            settings_key: settings_key.to_string(),
+            manifest,
+        }
     }
 
-    pub fn get_download_url(mut self, resolver: fn(&str, &str) -> Result<String, String>) -> Self {
-        self.get_download_url = resolver;
-        self
+    /// Asset key of the current platform, e.g. `linux-x64`
+    pub fn platform_string(&self) -> Result<String, InstallError> {
+        self.manifest.platform()
     }
 
-    pub fn get_platform_string(mut self, resolver: fn() -> Result<String, String>) -> Self {
-        self.get_platform_string = resolver;
-        self
+    /// File name of the release archive of `version` for `platform`, also what offline
+    /// users are told to side-load
+    pub fn archive_name(&self, version: &str, platform: &str) -> String {
+        self.manifest
+            .asset_name(version, platform)
+            .unwrap_or_else(|| format!("{}-{}-{}", self.prefix, platform, version))
     }
 
-    pub fn build(self) -> VersionDirConfig {
-        VersionDirConfig {
-            prefix: self.prefix,
-            github_repo: self.github_repo,
-            get_binary_path: self.get_binary_path,
-            binary_name_for_logging: self.binary_name_for_logging,
-            get_download_url: self.get_download_url,
-            get_platform_string: self.get_platform_string,
+    /// Choose the binary inside `version_dir` for the installed runtimes. Called once per
+    /// install; the choice is recorded in the install manifest.
+    pub fn choose_binary_path(
+        &self,
+        fs: &dyn FileSystem,
+        version_dir: &str,
+        runtimes: &DotnetRuntimes,
+    ) -> String {
+        self.manifest.entry_point(fs, version_dir, runtimes)
+    }
+
+    /// Files a complete install must contain given the chosen binary (relative to
+    /// version_dir), checked after extraction
+    pub fn expected_files(&self, binary: &str) -> Vec<ExpectedFile> {
+        self.manifest.expected_files(binary)
+    }
+
+    /// Binary path of an install: the one recorded when it was installed, or failing that
+    /// the one the manifest picks without knowing the runtimes
+    pub fn binary_path(&self, fs: &dyn FileSystem, version_dir: &str) -> String {
+        match InstallManifest::load(fs, version_dir).and_then(|manifest| manifest.binary) {
+            Some(binary) => format!("{}/{}", version_dir, binary),
+            None => self.choose_binary_path(fs, version_dir, &DotnetRuntimes::default()),
         }
     }
+
+    /// Look up and download the tool from `source` instead of its upstream
+    pub fn with_source(mut self, source: VersionSource) -> Self {
+        self.version_source = source;
+        self
+    }
 }
 
+/// Manifest name of netcoredbg
+pub const NETCOREDBG: &str = "netcoredbg";
+
+/// Manifest name of csharp-ls (razzmatazz/csharp-language-server from NuGet)
+pub const CSHARP_LANGUAGE_SERVER: &str = "csharp-language-server";
+
 /// Create a configuration for netcoredbg
 pub fn netcoredbg_config() -> VersionDirConfig {
-    VersionConfigBuilder::new("netcoredbg", "marcptrs/netcoredbg")
-        .get_platform_string(|| {
-            use zed_extension_api as zed;
-            let (platform, arch) = zed::current_platform();
-            let platform_str = match (platform, arch) {
-                (zed::Os::Linux, zed::Architecture::Aarch64) => "linux-arm64",
-                (zed::Os::Linux, zed::Architecture::X86) => "linux-x86",
-                (zed::Os::Linux, zed::Architecture::X8664) => "linux-x64",
-                (zed::Os::Mac, zed::Architecture::Aarch64) => "osx-arm64",
-                (zed::Os::Mac, zed::Architecture::X86) => "osx-x86",
-                (zed::Os::Mac, zed::Architecture::X8664) => "osx-x64",
-                (zed::Os::Windows, zed::Architecture::Aarch64) => "win-arm64",
-                (zed::Os::Windows, zed::Architecture::X86) => "win-x86",
-                (zed::Os::Windows, zed::Architecture::X8664) => "win-x64",
-            };
-            Ok(platform_str.to_string())
-        })
-        .get_download_url(|_version: &str, platform: &str| {
-            use zed_extension_api as zed;
-
-            // Fetch the latest release from GitHub
-            let release = zed::latest_github_release(
-                "marcptrs/netcoredbg",
-                zed::GithubReleaseOptions {
-                    require_assets: true,
-                    pre_release: false,
-                },
-            )
-            .map_err(|e| format!("failed to fetch netcoredbg release: {}", e))?;
-
-            // Windows uses .zip, Unix platforms use .tar.gz
-            let (current_platform, _) = zed::current_platform();
-            let extension = match current_platform {
-                zed::Os::Windows => "zip",
-                _ => "tar.gz",
-            };
-
-            // Build the asset name we're looking for
-            let asset_name = format!("netcoredbg-{}.{}", platform, extension);
-
-            // Find the matching asset
-            let asset = release
-                .assets
-                .iter()
-                .find(|asset| asset.name == asset_name)
-                .ok_or_else(|| {
-                    format!(
-                        "no compatible netcoredbg asset found for platform '{}'. available: [{}]",
-                        platform,
-                        release
-                            .assets
-                            .iter()
-                            .map(|a| a.name.as_str())
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    )
-                })?;
-
-            Ok(asset.download_url.clone())
-        })
-        .get_binary_path(|version_dir: &str| {
-            use zed_extension_api as zed;
-            let (platform, _) = zed::current_platform();
-            let binary_name = match platform {
-                zed::Os::Windows => "netcoredbg.exe",
-                _ => "netcoredbg",
-            };
-            format!("{}/{}", version_dir, binary_name)
-        })
-        .binary_name_for_logging("netcoredbg")
-        .build()
+    tool_config(NETCOREDBG, None).expect("built-in netcoredbg manifest is valid")
 }
 
-/// Create a configuration for csharp-ls (razzmatazz/csharp-language-server from NuGet)
+/// Create a configuration for csharp-ls
+///
+/// Versions are listed from the same flat container the package is downloaded from,
+/// so discovery can't drift from what is actually published.
 pub fn csharp_language_server_config() -> VersionDirConfig {
-    VersionConfigBuilder::new("csharp-language-server", "razzmatazz/csharp-language-server")
-        .get_platform_string(|| {
-            Ok("nuget".to_string())
-        })
-        .get_download_url(|version: &str, _platform: &str| {
-            let url = format!(
-                "https://www.nuget.org/api/v2/package/csharp-ls/{}",
-                version
-            );
-            Ok(url)
-        })
-        .get_binary_path(|version_dir: &str| {
-            use zed_extension_api as zed;
-            let (platform, _) = zed::current_platform();
-            let binary_name = match platform {
-                _ => "CSharpLanguageServer.dll",
-            };
-            format!("{}/tools/net9.0/any/{}", version_dir, binary_name)
-        })
-        .binary_name_for_logging("csharp-language-server")
-        .build()
+    tool_config(CSHARP_LANGUAGE_SERVER, None)
+        .expect("built-in csharp-language-server manifest is valid")
+}
+
+/// Configuration of a built-in tool, with manifest `overrides` from settings applied
+pub fn tool_config(
+    name: &str,
+    overrides: Option<&zed::serde_json::Value>,
+) -> Result<VersionDirConfig, String> {
+    let settings_key = match name {
+        NETCOREDBG => format!("lsp.{}.settings.debugger", crate::LANGUAGE_SERVER_NAME),
+        _ => format!("lsp.{}.settings", crate::LANGUAGE_SERVER_NAME),
+    };
+    Ok(VersionDirConfig::new(
+        ToolManifest::builtin(name, overrides)?,
+        &settings_key,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_basic_credentials() {
+        assert_eq!(
+            HttpAuth::basic("Aladdin", "open sesame").value,
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
 }